@@ -0,0 +1,107 @@
+use anyhow::{bail, Result};
+use kuon::OAuthToken;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use tokio::fs::File;
+use tokio::io::BufReader;
+
+use crate::context::Context;
+use crate::utils::crypto::{self, Encrypted};
+use crate::utils::file::write_private;
+use crate::utils::stdio::read;
+
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Named OAuth tokens stored in the token file.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Accounts {
+    pub active: Option<String>,
+    pub profiles: BTreeMap<String, OAuthToken>,
+    /// Set when the token file is encrypted, so that saving encrypts it again.
+    #[serde(skip)]
+    pub passphrase: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum StoredAccounts {
+    Encrypted { encrypted: Encrypted },
+    Profiles(Accounts),
+    // Token files written before profiles existed hold a single bare token.
+    Legacy(OAuthToken),
+}
+
+impl Accounts {
+    pub async fn load() -> Result<Self> {
+        let file = match File::open(Context::oauth_token_path()?).await {
+            Ok(file) => file,
+            Err(_) => return Ok(Self::default()),
+        };
+        let mut reader = BufReader::new(file);
+        let json = read(&mut reader).await?;
+        let stored: StoredAccounts = serde_json::from_str(&json)?;
+
+        let accounts = match stored {
+            StoredAccounts::Encrypted { encrypted } => {
+                let passphrase = crypto::passphrase("Passphrase for petit accounts: ")?;
+                let json = crypto::decrypt(&passphrase, &encrypted)?;
+                Self {
+                    passphrase: Some(passphrase),
+                    ..serde_json::from_slice(&json)?
+                }
+            }
+            StoredAccounts::Profiles(accounts) => accounts,
+            StoredAccounts::Legacy(token) => {
+                let mut accounts = Self::default();
+                accounts.insert(DEFAULT_PROFILE, token);
+                accounts
+            }
+        };
+
+        Ok(accounts)
+    }
+
+    pub async fn save(&self) -> Result<()> {
+        let mut serialized = serde_json::to_string(self)?;
+        if let Some(passphrase) = &self.passphrase {
+            let encrypted = crypto::encrypt(passphrase, serialized.as_bytes())?;
+            serialized = serde_json::to_string(&StoredAccounts::Encrypted { encrypted })?;
+        }
+        write_private(&Context::oauth_token_path()?, serialized.as_bytes()).await
+    }
+
+    /// Returns the profile selected by `name`, or the active one when `name` is `None`.
+    pub fn get(&self, name: Option<&str>) -> Option<(&str, &OAuthToken)> {
+        let name = name.or(self.active.as_deref())?;
+        self.profiles
+            .get_key_value(name)
+            .map(|(name, token)| (name.as_str(), token))
+    }
+
+    /// Adds or replaces a profile and makes it the active one.
+    pub fn insert(&mut self, name: &str, token: OAuthToken) {
+        self.profiles.insert(name.to_string(), token);
+        self.active = Some(name.to_string());
+    }
+
+    pub fn switch(&mut self, name: &str) -> Result<()> {
+        if !self.profiles.contains_key(name) {
+            bail!("No such account: {}", name);
+        }
+        self.active = Some(name.to_string());
+        Ok(())
+    }
+
+    /// Removes a profile. When it was the active one, the first remaining profile
+    /// becomes active and is returned, so that the caller can tell the user.
+    pub fn remove(&mut self, name: &str) -> Result<Option<&str>> {
+        if self.profiles.remove(name).is_none() {
+            bail!("No such account: {}", name);
+        }
+        if self.active.as_deref() != Some(name) {
+            return Ok(None);
+        }
+        self.active = self.profiles.keys().next().cloned();
+        Ok(self.active.as_deref())
+    }
+}
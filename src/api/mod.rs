@@ -0,0 +1,444 @@
+use anyhow::{bail, Error as AnyError, Result};
+use chrono::{DateTime, Local, TimeZone, Utc};
+use kuon::{Error, OAuthToken, TrimTweet, TwitterAPIErrorMessage, User};
+use reqwest::{header::AUTHORIZATION, Method, StatusCode};
+use serde::{de::DeserializeOwned, Deserialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::context::Credentials;
+
+mod oauth;
+
+pub const DEFAULT_BASE_URL: &str = "https://api.twitter.com";
+
+/// A Twitter API v1.1 client that signs requests itself, so that the
+/// endpoint can point at something other than api.twitter.com.
+#[derive(Debug, Clone)]
+pub struct Client {
+    credentials: Credentials,
+    token: OAuthToken,
+    base_url: String,
+    http: reqwest::Client,
+    rate_limit: Arc<Mutex<Option<RateLimit>>>,
+}
+
+#[derive(Deserialize)]
+struct SearchResponse {
+    statuses: Vec<TrimTweet>,
+}
+
+/// The API answered 429 Too Many Requests.
+#[derive(Debug, Clone)]
+pub struct RateLimited {
+    /// When the current rate limit window ends, from `x-rate-limit-reset`.
+    pub reset: Option<DateTime<Utc>>,
+}
+
+impl RateLimited {
+    /// How long until requests are accepted again.
+    pub fn wait(&self) -> Option<Duration> {
+        self.reset.and_then(|x| (x - Utc::now()).to_std().ok())
+    }
+}
+
+impl fmt::Display for RateLimited {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.reset {
+            Some(reset) => write!(
+                f,
+                "Rate limit exceeded. try again after {}",
+                reset.with_timezone(&Local).format("%H:%M:%S")
+            ),
+            None => write!(f, "Rate limit exceeded. try again later"),
+        }
+    }
+}
+
+impl std::error::Error for RateLimited {}
+
+/// The rate limit window of the last request, from the `x-rate-limit-*` headers.
+#[derive(Debug, Clone)]
+pub struct RateLimit {
+    pub remaining: u32,
+    pub reset: Option<DateTime<Utc>>,
+}
+
+impl RateLimit {
+    /// How long to wait between requests so that the remaining ones last until the
+    /// window ends.
+    pub fn pace(&self) -> Option<Duration> {
+        let left = self.reset.and_then(|x| (x - Utc::now()).to_std().ok())?;
+        Some(left / self.remaining.max(1))
+    }
+}
+
+/// The API answered with a 5xx status, so the request may succeed later.
+#[derive(Debug, Clone)]
+pub struct ServerError {
+    pub status: StatusCode,
+}
+
+impl fmt::Display for ServerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Twitter is unavailable ({}). try again later",
+            self.status
+        )
+    }
+}
+
+impl std::error::Error for ServerError {}
+
+/// The tweet listings that can be paged through like the home timeline.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Timeline {
+    Home,
+    Mentions,
+    User(String),
+    List(ListId),
+    /// Tweets liked by the given user, or by the logged in one.
+    Likes(Option<String>),
+}
+
+/// A list, by its numeric id or as `owner/slug`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ListId {
+    Id(u64),
+    Slug { owner: String, slug: String },
+}
+
+impl FromStr for ListId {
+    type Err = AnyError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if let Ok(id) = s.parse::<u64>() {
+            return Ok(Self::Id(id));
+        }
+        match s.split_once('/') {
+            Some((owner, slug)) if !owner.is_empty() && !slug.is_empty() => Ok(Self::Slug {
+                owner: owner.trim_start_matches('@').to_string(),
+                slug: slug.to_string(),
+            }),
+            _ => bail!("invalid list: {} (use owner/slug or the list id)", s),
+        }
+    }
+}
+
+impl Timeline {
+    /// Names the timeline in cache keys and `cache.endpoint_ttl`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Home => "home",
+            Self::Mentions => "mentions",
+            Self::User(_) => "user",
+            Self::List(_) => "list",
+            Self::Likes(_) => "likes",
+        }
+    }
+
+    fn path(&self) -> &'static str {
+        match self {
+            Self::Home => "statuses/home_timeline.json",
+            Self::Mentions => "statuses/mentions_timeline.json",
+            Self::User(_) => "statuses/user_timeline.json",
+            Self::List(_) => "lists/statuses.json",
+            Self::Likes(_) => "favorites/list.json",
+        }
+    }
+
+    /// The parameters that tell one timeline of a kind from another.
+    pub fn params(&self) -> Vec<(&'static str, String)> {
+        match self {
+            Self::Home | Self::Mentions | Self::Likes(None) => vec![],
+            Self::User(screen_name) | Self::Likes(Some(screen_name)) => {
+                vec![("screen_name", screen_name.clone())]
+            }
+            Self::List(ListId::Id(id)) => vec![("list_id", id.to_string())],
+            Self::List(ListId::Slug { owner, slug }) => {
+                vec![("owner_screen_name", owner.clone()), ("slug", slug.clone())]
+            }
+        }
+    }
+}
+
+/// Milliseconds since the Unix epoch at which tweet ids start counting.
+const TWITTER_EPOCH: i64 = 1_288_834_974_657;
+
+/// The smallest tweet id that can be given to a tweet posted at `time`, since ids
+/// carry their creation time in the bits above the lowest 22.
+pub fn snowflake_id(time: DateTime<Utc>) -> u64 {
+    let ms = (time.timestamp_millis() - TWITTER_EPOCH).max(0) as u64;
+    ms << 22
+}
+
+/// Paging parameters shared by the timeline endpoints.
+#[derive(Debug, Clone, Default)]
+pub struct Page {
+    pub count: Option<u64>,
+    pub since_id: Option<u64>,
+    pub max_id: Option<u64>,
+}
+
+impl Page {
+    fn params(&self) -> HashMap<&'static str, String> {
+        let mut params = HashMap::new();
+        if let Some(count) = self.count {
+            params.insert("count", count.to_string());
+        }
+        if let Some(since_id) = self.since_id {
+            params.insert("since_id", since_id.to_string());
+        }
+        if let Some(max_id) = self.max_id {
+            params.insert("max_id", max_id.to_string());
+        }
+        params
+    }
+}
+
+impl Client {
+    pub fn new(credentials: Credentials, token: OAuthToken, base_url: &str) -> Self {
+        Self {
+            credentials,
+            token,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            http: reqwest::Client::builder()
+                .connect_timeout(Duration::from_secs(10))
+                .build()
+                .unwrap_or_default(),
+            rate_limit: Arc::default(),
+        }
+    }
+
+    /// The rate limit reported with the last response, if the API sent one.
+    pub fn rate_limit(&self) -> Option<RateLimit> {
+        self.rate_limit.lock().ok()?.clone()
+    }
+
+    pub fn oauth_token(&self) -> &OAuthToken {
+        &self.token
+    }
+
+    pub async fn timeline(&self, timeline: &Timeline, page: &Page) -> Result<Vec<TrimTweet>> {
+        let mut params = page.params();
+        params.extend(timeline.params());
+        if let Timeline::List(_) = timeline {
+            // Lists leave retweets out unless asked for, unlike the other timelines.
+            params.insert("include_rts", String::from("true"));
+        }
+        self.get(timeline.path(), &params).await
+    }
+
+    pub async fn search_tweets(&self, query: &str) -> Result<Vec<TrimTweet>> {
+        let mut params = HashMap::new();
+        params.insert("q", query.to_string());
+        let response: SearchResponse = self.get("search/tweets.json", &params).await?;
+        Ok(response.statuses)
+    }
+
+    /// Searches newest first, so that results can be paged with `since_id` and `max_id`.
+    pub async fn search_recent(&self, query: &str, page: &Page) -> Result<Vec<TrimTweet>> {
+        let mut params = page.params();
+        params.insert("q", query.to_string());
+        params.insert("result_type", String::from("recent"));
+        let response: SearchResponse = self.get("search/tweets.json", &params).await?;
+        Ok(response.statuses)
+    }
+
+    pub async fn tweet(&self, status: &str) -> Result<TrimTweet> {
+        let mut params = HashMap::new();
+        params.insert("status", status.to_string());
+        self.post("statuses/update.json", &params).await
+    }
+
+    pub async fn verify_credentials(&self) -> Result<User> {
+        self.get("account/verify_credentials.json", &HashMap::new())
+            .await
+    }
+
+    pub async fn invalidate_token(&self) -> Result<()> {
+        self.post::<serde_json::Value>("oauth/invalidate_token", &HashMap::new())
+            .await?;
+        Ok(())
+    }
+
+    async fn get<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        params: &HashMap<&str, String>,
+    ) -> Result<T> {
+        self.request(Method::GET, path, params).await
+    }
+
+    async fn post<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        params: &HashMap<&str, String>,
+    ) -> Result<T> {
+        self.request(Method::POST, path, params).await
+    }
+
+    async fn request<T: DeserializeOwned>(
+        &self,
+        method: Method,
+        path: &str,
+        params: &HashMap<&str, String>,
+    ) -> Result<T> {
+        let url = format!("{}/1.1/{}", self.base_url, path);
+        let authorization = oauth::authorization(
+            &self.credentials,
+            &self.token,
+            method.as_str(),
+            &url,
+            params,
+        );
+
+        let response = self
+            .http
+            .request(method, &url)
+            .query(params)
+            .header(AUTHORIZATION, authorization)
+            .send()
+            .await
+            .map_err(Error::HTTPRequestError)?;
+        let header = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|x| x.to_str().ok())
+                .and_then(|x| x.parse::<i64>().ok())
+        };
+        let reset = header("x-rate-limit-reset").map(|x| Utc.timestamp(x, 0));
+        if let Some(remaining) = header("x-rate-limit-remaining") {
+            if let Ok(mut rate_limit) = self.rate_limit.lock() {
+                *rate_limit = Some(RateLimit {
+                    remaining: remaining.max(0) as u32,
+                    reset,
+                });
+            }
+        }
+        if response.status() == StatusCode::TOO_MANY_REQUESTS {
+            return Err(RateLimited { reset }.into());
+        }
+        if response.status().is_server_error() {
+            return Err(ServerError {
+                status: response.status(),
+            }
+            .into());
+        }
+        let text = response.text().await.map_err(Error::HTTPRequestError)?;
+
+        serde_json::from_str::<T>(&text).map_err(|e| {
+            let err = match serde_json::from_str::<TwitterAPIErrorMessage>(&text) {
+                Ok(v) => Error::TwitterAPIError(v, format!("{:?}", params)),
+                Err(_) => Error::JsonParsingError(e.into(), text),
+            };
+            if is_token_rejected(&err) {
+                AnyError::new(err)
+                    .context("The access token was rejected. run \"petit login\" again")
+            } else {
+                err.into()
+            }
+        })
+    }
+}
+
+/// Starts a login with a request token, to send the user to [`authorize_url`] with.
+/// `callback` is the URL the verifier is sent to, or `oob` to show a PIN instead.
+pub async fn request_token(
+    credentials: &Credentials,
+    base_url: &str,
+    callback: &str,
+) -> Result<OAuthToken> {
+    let extra = vec![("oauth_callback", callback.to_string())];
+    token_request(credentials, base_url, "oauth/request_token", None, extra).await
+}
+
+/// Trades the request token and the verifier of the user for an access token.
+pub async fn access_token(
+    credentials: &Credentials,
+    base_url: &str,
+    request_token: &OAuthToken,
+    verifier: &str,
+) -> Result<OAuthToken> {
+    let extra = vec![("oauth_verifier", verifier.trim().to_string())];
+    token_request(
+        credentials,
+        base_url,
+        "oauth/access_token",
+        Some(request_token),
+        extra,
+    )
+    .await
+}
+
+pub fn authorize_url(base_url: &str, request_token: &OAuthToken) -> String {
+    format!(
+        "{}/oauth/authorize?oauth_token={}",
+        base_url, request_token.token
+    )
+}
+
+/// Calls a step of the login flow, which answers with a form-encoded token.
+async fn token_request(
+    credentials: &Credentials,
+    base_url: &str,
+    path: &str,
+    token: Option<&OAuthToken>,
+    extra: Vec<(&'static str, String)>,
+) -> Result<OAuthToken> {
+    let endpoint = format!("{}/{}", base_url, path);
+    let authorization = oauth::sign(
+        credentials,
+        token,
+        extra,
+        "POST",
+        &endpoint,
+        &HashMap::new(),
+    );
+    let response = reqwest::Client::new()
+        .post(&endpoint)
+        .header(AUTHORIZATION, authorization)
+        .send()
+        .await
+        .map_err(Error::HTTPRequestError)?;
+    let status = response.status();
+    let text = response.text().await.map_err(Error::HTTPRequestError)?;
+    if !status.is_success() {
+        bail!("{} failed ({}): {}", path, status, text);
+    }
+
+    let params = url::form_urlencoded::parse(text.as_bytes())
+        .into_owned()
+        .collect::<HashMap<_, _>>();
+    match (params.get("oauth_token"), params.get("oauth_token_secret")) {
+        (Some(token), Some(secret)) => Ok(OAuthToken {
+            token: token.clone(),
+            secret: secret.clone(),
+        }),
+        _ => bail!("Unexpected response from {}: {}", path, text),
+    }
+}
+
+/// Whether the request never reached the API, as when the network is down.
+pub fn is_connection_error(err: &AnyError) -> bool {
+    match err.downcast_ref::<Error>() {
+        Some(Error::HTTPRequestError(err)) => err.is_connect() || err.is_timeout(),
+        _ => false,
+    }
+}
+
+/// Whether the API refused the access token itself, as opposed to any other failure.
+pub fn is_token_rejected(err: &Error) -> bool {
+    match err {
+        // 32: Could not authenticate you, 89: Invalid or expired token
+        Error::TwitterAPIError(message, _) => {
+            message.errors.iter().any(|x| x.code == 32 || x.code == 89)
+        }
+        _ => false,
+    }
+}
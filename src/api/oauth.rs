@@ -0,0 +1,81 @@
+use chrono::Utc;
+use hmac::{Hmac, Mac, NewMac};
+use kuon::{OAuthToken, TwitterAPI};
+use rand::{distributions::Alphanumeric, Rng};
+use sha1::Sha1;
+use std::collections::HashMap;
+
+use crate::context::Credentials;
+
+fn encode(s: &str) -> String {
+    TwitterAPI::encode(s).to_string()
+}
+
+/// Builds the OAuth 1.0a `Authorization` header for a request signed with HMAC-SHA1.
+pub fn authorization(
+    credentials: &Credentials,
+    token: &OAuthToken,
+    method: &str,
+    url: &str,
+    params: &HashMap<&str, String>,
+) -> String {
+    sign(credentials, Some(token), Vec::new(), method, url, params)
+}
+
+/// Signs a request of the login flow. `token` is the request token once there
+/// is one, and `extra` carries `oauth_callback` or `oauth_verifier`.
+pub fn sign(
+    credentials: &Credentials,
+    token: Option<&OAuthToken>,
+    extra: Vec<(&'static str, String)>,
+    method: &str,
+    url: &str,
+    params: &HashMap<&str, String>,
+) -> String {
+    let nonce = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(32)
+        .map(char::from)
+        .collect::<String>();
+    let mut oauth_params = vec![
+        ("oauth_consumer_key", credentials.api_key.clone()),
+        ("oauth_nonce", nonce),
+        ("oauth_signature_method", String::from("HMAC-SHA1")),
+        ("oauth_timestamp", Utc::now().timestamp().to_string()),
+        ("oauth_version", String::from("1.0")),
+    ];
+    if let Some(token) = token {
+        oauth_params.push(("oauth_token", token.token.clone()));
+    }
+    oauth_params.extend(extra);
+
+    let mut signed = params
+        .iter()
+        .map(|(k, v)| (encode(k), encode(v)))
+        .chain(oauth_params.iter().map(|(k, v)| (encode(k), encode(v))))
+        .collect::<Vec<_>>();
+    signed.sort();
+    let param_string = signed
+        .iter()
+        .map(|(k, v)| format!("{}={}", k, v))
+        .collect::<Vec<_>>()
+        .join("&");
+
+    let base = format!("{}&{}&{}", method, encode(url), encode(&param_string));
+    let key = format!(
+        "{}&{}",
+        encode(&credentials.api_secret),
+        encode(token.map(|x| x.secret.as_str()).unwrap_or_default())
+    );
+    let mut mac = Hmac::<Sha1>::new_varkey(key.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(base.as_bytes());
+    let signature = base64::encode(mac.finalize().into_bytes());
+    oauth_params.push(("oauth_signature", signature));
+
+    let header = oauth_params
+        .iter()
+        .map(|(k, v)| format!("{}=\"{}\"", k, encode(v)))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!("OAuth {}", header)
+}
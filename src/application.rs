@@ -1,11 +1,23 @@
 use clap::Clap;
 
-use crate::{context::Context, sub_command::SubCommand};
+use crate::{context::Context, sub_command::SubCommand, utils::color::ColorChoice};
 use anyhow::Result;
 
 #[derive(Debug, Clap)]
 #[clap(version = "0.0.2", author = "uzimaru0000<shuji365630@gmail.com>")]
 pub struct Application {
+    #[clap(long, global = true)]
+    pub account: Option<String>,
+    #[clap(long, global = true, env = "PETIT_API_KEY")]
+    pub api_key: Option<String>,
+    #[clap(long, global = true, env = "PETIT_API_SECRET", hide_env_values = true)]
+    pub api_secret: Option<String>,
+    /// Do not call the API; serve the cache and archive and queue tweets.
+    #[clap(long, global = true)]
+    pub offline: bool,
+    /// When to color output: auto, always or never
+    #[clap(long, global = true, default_value = "auto")]
+    pub color: ColorChoice,
     #[clap(subcommand)]
     pub subcmd: SubCommand,
 }
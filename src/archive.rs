@@ -0,0 +1,176 @@
+use anyhow::{Context as _, Result};
+use kuon::TrimTweet;
+use rusqlite::{params, params_from_iter, Connection};
+
+use crate::component::tweet::TweetView;
+use crate::context::Context;
+use crate::utils::file::make_private;
+
+/// Every tweet petit has fetched, searchable without the API.
+pub struct Archive {
+    conn: Connection,
+}
+
+impl Archive {
+    pub fn open() -> Result<Self> {
+        let path = Context::archive_path()?;
+        let conn = Connection::open(&path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        make_private(&path)?;
+        // Another petit process may be writing at the same time.
+        conn.busy_timeout(std::time::Duration::from_secs(5))?;
+        // The trigram tokenizer matches substrings, so languages without
+        // spaces between words, such as Japanese, can be searched too.
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS tweets (
+                id INTEGER PRIMARY KEY,
+                json TEXT NOT NULL,
+                screen_name TEXT
+            );
+            CREATE VIRTUAL TABLE IF NOT EXISTS tweets_fts USING fts5(
+                text, user_name, screen_name, tokenize = 'trigram'
+            );",
+        )?;
+        Self::migrate(&conn)?;
+        conn.execute_batch(
+            "CREATE INDEX IF NOT EXISTS tweets_screen_name
+             ON tweets (screen_name COLLATE NOCASE);",
+        )?;
+
+        Ok(Self { conn })
+    }
+
+    /// Adds the column of the posting user to archives written before it existed.
+    fn migrate(conn: &Connection) -> Result<()> {
+        let has_screen_name: bool = conn.query_row(
+            "SELECT COUNT(*) > 0 FROM pragma_table_info('tweets') WHERE name = 'screen_name'",
+            [],
+            |row| row.get(0),
+        )?;
+        if !has_screen_name {
+            conn.execute_batch(
+                "BEGIN;
+                 ALTER TABLE tweets ADD COLUMN screen_name TEXT;
+                 UPDATE tweets SET screen_name = json_extract(json, '$.user.screen_name');
+                 COMMIT;",
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Stores fetched tweets on the side. The archive is not needed to show them, so
+    /// a failure, such as a locked database or a full disk, is only reported.
+    pub fn keep(tweets: &[TrimTweet]) {
+        if let Err(err) = Self::open().and_then(|mut x| x.insert(tweets)) {
+            eprintln!("Failed to archive tweets: {:#}", err);
+        }
+    }
+
+    /// Stores tweets, replacing earlier copies of the same tweet.
+    pub fn insert(&mut self, tweets: &[TrimTweet]) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        for tweet in tweets {
+            let id = tweet.id as i64;
+            let view = TweetView::from(tweet);
+            // The columns of `tweets_fts` describe the shown tweet, which is the
+            // original one for a retweet, so the posting user is kept here.
+            tx.execute(
+                "INSERT OR REPLACE INTO tweets (id, json, screen_name) VALUES (?1, ?2, ?3)",
+                params![id, serde_json::to_string(tweet)?, tweet.user.screen_name],
+            )?;
+            tx.execute("DELETE FROM tweets_fts WHERE rowid = ?1", params![id])?;
+            tx.execute(
+                "INSERT INTO tweets_fts (rowid, text, user_name, screen_name) VALUES (?1, ?2, ?3, ?4)",
+                params![id, view.tweet, view.user_name, view.screen_name],
+            )?;
+        }
+        tx.commit()?;
+
+        Ok(())
+    }
+
+    /// Finds tweets containing every word of `query`, newest first.
+    pub fn search(&self, query: &str, limit: usize) -> Result<Vec<TrimTweet>> {
+        // Trigrams cannot match words shorter than three characters, so those
+        // are looked up with LIKE, which scans instead of using the index.
+        let (long, short): (Vec<_>, Vec<_>) = query
+            .split_whitespace()
+            .partition(|x| x.chars().count() >= 3);
+        let mut conditions = Vec::new();
+        let mut values = Vec::new();
+        if !long.is_empty() {
+            values.push(Self::match_expr(&long));
+            conditions.push(format!("tweets_fts MATCH ?{}", values.len()));
+        }
+        for word in short {
+            values.push(format!("%{}%", Self::escape_like(word)));
+            conditions.push(format!(
+                "(tweets_fts.text LIKE ?{0} ESCAPE '\\' \
+                 OR tweets_fts.user_name LIKE ?{0} ESCAPE '\\' \
+                 OR tweets_fts.screen_name LIKE ?{0} ESCAPE '\\')",
+                values.len()
+            ));
+        }
+        if conditions.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT tweets.json FROM tweets_fts
+             JOIN tweets ON tweets.id = tweets_fts.rowid
+             WHERE {}
+             ORDER BY tweets.id DESC
+             LIMIT {}",
+            conditions.join(" AND "),
+            limit
+        ))?;
+        let rows = stmt.query_map(params_from_iter(values), |row| row.get::<_, String>(0))?;
+
+        let mut tweets = Vec::new();
+        for json in rows {
+            tweets.push(serde_json::from_str(&json?)?);
+        }
+        Ok(tweets)
+    }
+
+    /// Finds tweets posted by `screen_name` with ids in `(since_id, max_id]`, newest first.
+    pub fn user_tweets(
+        &self,
+        screen_name: &str,
+        since_id: Option<u64>,
+        max_id: Option<u64>,
+    ) -> Result<Vec<TrimTweet>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT json FROM tweets
+             WHERE screen_name = ?1 COLLATE NOCASE
+               AND id > ?2 AND id <= ?3
+             ORDER BY id DESC",
+        )?;
+        let since_id = since_id.map_or(0, |x| x as i64);
+        let max_id = max_id.map_or(i64::MAX, |x| x.min(i64::MAX as u64) as i64);
+        let rows = stmt.query_map(params![screen_name, since_id, max_id], |row| {
+            row.get::<_, String>(0)
+        })?;
+
+        let mut tweets = Vec::new();
+        for json in rows {
+            tweets.push(serde_json::from_str(&json?)?);
+        }
+        Ok(tweets)
+    }
+
+    /// Quotes every word so that the query is never parsed as FTS5 syntax.
+    fn match_expr(words: &[&str]) -> String {
+        words
+            .iter()
+            .map(|x| format!("\"{}\"", x.replace('"', "\"\"")))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    fn escape_like(word: &str) -> String {
+        word.replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_")
+    }
+}
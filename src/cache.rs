@@ -0,0 +1,382 @@
+use anyhow::{bail, Context as _, Result};
+use chrono::{DateTime, Utc};
+use kuon::TrimTweet;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashSet};
+use std::path::PathBuf;
+use tokio::io::AsyncWriteExt;
+
+use crate::account::DEFAULT_PROFILE;
+use crate::context::Context;
+use crate::utils::file::{lock, write_private};
+
+pub const HOME: &str = "home";
+pub const SEARCH: &str = "search";
+
+/// Bumped whenever the format of the cache file changes. Files of older
+/// versions are converted on load.
+pub const CACHE_VERSION: u64 = 2;
+
+/// Responses of the API for one account, keyed by endpoint and parameters.
+#[derive(Debug, Clone)]
+pub struct Cache {
+    pub account: Option<String>,
+    pub entries: BTreeMap<String, CacheEntry>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct CacheEntry {
+    pub latest_call: Option<DateTime<Utc>>,
+    /// Cached tweets, newest first.
+    pub timeline: Vec<TrimTweet>,
+    #[serde(default)]
+    pub hits: u64,
+    #[serde(default)]
+    pub misses: u64,
+}
+
+/// The cache file. Every account has its own entries, so that switching
+/// accounts does not throw the cache of the other ones away.
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct CacheFile {
+    version: u64,
+    accounts: BTreeMap<String, BTreeMap<String, CacheEntry>>,
+}
+
+/// Cache files of version 1, holding the entries of the last account only.
+#[derive(Deserialize)]
+struct CacheFileV1 {
+    account: Option<String>,
+    entries: BTreeMap<String, CacheEntry>,
+}
+
+/// Cache files without a version.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum UnversionedCache {
+    Entries {
+        #[serde(default)]
+        account: Option<String>,
+        entries: BTreeMap<String, CacheEntry>,
+    },
+    // Caches written before entries existed only hold the home timeline.
+    Legacy {
+        #[serde(default)]
+        account: Option<String>,
+        latest_call: Option<DateTime<Utc>>,
+        timeline: Vec<TrimTweet>,
+    },
+}
+
+impl Cache {
+    pub fn new(account: Option<String>) -> Self {
+        Self {
+            account,
+            entries: BTreeMap::new(),
+        }
+    }
+
+    /// Builds the key of an endpoint called with `params`, such as `search?q=rust`.
+    pub fn key(endpoint: &str, params: &[(&str, &str)]) -> String {
+        if params.is_empty() {
+            return endpoint.to_string();
+        }
+        let query = params
+            .iter()
+            .map(|(k, v)| format!("{}={}", k, v))
+            .collect::<Vec<_>>()
+            .join("&");
+        format!("{}?{}", endpoint, query)
+    }
+
+    pub fn entry(&mut self, key: &str) -> &mut CacheEntry {
+        self.entries.entry(key.to_string()).or_default()
+    }
+
+    /// Reads the cache of `account`, if anything is cached for it.
+    pub async fn load(account: Option<&str>) -> Result<Option<Self>> {
+        let account = match account {
+            Some(account) => account,
+            None => return Ok(None),
+        };
+        let mut file = CacheFile::read(false).await?;
+        Ok(file.accounts.remove(account).map(|entries| Self {
+            account: Some(account.to_string()),
+            entries,
+        }))
+    }
+
+    /// Reads the caches of every account.
+    pub async fn load_all() -> Result<Vec<Self>> {
+        let file = CacheFile::read(false).await?;
+        Ok(file
+            .accounts
+            .into_iter()
+            .map(|(account, entries)| Self {
+                account: Some(account),
+                entries,
+            })
+            .collect())
+    }
+
+    /// Applies `f` to the latest cache of `account` and writes it back while
+    /// holding the cache lock, so that concurrent runs do not lose each other's updates.
+    pub async fn update<F: FnOnce(&mut Self)>(account: Option<String>, f: F) -> Result<Self> {
+        let lock = lock(&lock_path()?).await?;
+
+        let mut file = CacheFile::read(true).await?;
+        let mut cache = Self {
+            entries: account
+                .as_ref()
+                .and_then(|x| file.accounts.remove(x))
+                .unwrap_or_default(),
+            ..Self::new(account)
+        };
+        f(&mut cache);
+        match &cache.account {
+            Some(account) if !cache.entries.is_empty() => {
+                file.accounts.insert(account.clone(), cache.entries.clone());
+            }
+            _ => {}
+        }
+        file.write().await?;
+
+        // Closing the file releases the lock.
+        drop(lock);
+        Ok(cache)
+    }
+
+    /// Returns the entry of `key` if it was fetched less than `ttl` seconds ago,
+    /// logging the hit. The cache file itself is left as it is.
+    pub async fn hit(account: Option<String>, key: &str, ttl: i64) -> Result<Option<CacheEntry>> {
+        let lock = lock(&lock_path()?).await?;
+
+        let mut file = CacheFile::read(true).await?;
+        let mut entry = account
+            .as_deref()
+            .and_then(|x| file.accounts.remove(x))
+            .and_then(|mut x| x.remove(key))
+            .filter(|x| x.is_fresh(ttl));
+        if let Some(entry) = &mut entry {
+            let path = hits_path()?;
+            let mut options = tokio::fs::OpenOptions::new();
+            options.append(true).create(true);
+            #[cfg(unix)]
+            options.mode(0o600);
+            let mut log = options
+                .open(&path)
+                .await
+                .with_context(|| format!("Failed to open {}", path.display()))?;
+            let line = serde_json::to_string(&(account.unwrap_or_default(), key))? + "\n";
+            log.write_all(line.as_bytes()).await?;
+            entry.hits += 1;
+        }
+
+        drop(lock);
+        Ok(entry)
+    }
+
+    /// Records a call to the API for `key`, letting `f` put the response into the entry.
+    pub async fn store<F: FnOnce(&mut CacheEntry)>(
+        account: Option<String>,
+        key: &str,
+        f: F,
+    ) -> Result<CacheEntry> {
+        let mut cache = Self::update(account, |cache| {
+            let entry = cache.entry(key);
+            entry.misses += 1;
+            entry.latest_call = Some(Utc::now());
+            f(entry);
+        })
+        .await?;
+        Ok(cache.entries.remove(key).unwrap_or_default())
+    }
+
+    /// Removes everything cached for `account`.
+    pub async fn remove(account: &str) -> Result<()> {
+        Self::update(Some(account.to_string()), |cache| cache.entries.clear()).await?;
+        Ok(())
+    }
+
+    /// Removes the cache of every account.
+    pub async fn remove_all() -> Result<()> {
+        let lock = lock(&lock_path()?).await?;
+        remove_if_exists(&Context::cache_file_path()?).await?;
+        remove_if_exists(&hits_path()?).await?;
+        drop(lock);
+        Ok(())
+    }
+}
+
+impl CacheFile {
+    /// Reads the cache file. A file written by a newer petit is an error, and one
+    /// that cannot be parsed is read as empty. Only a caller holding the cache lock
+    /// moves it aside, so that it is not overwritten by the next write.
+    async fn read(locked: bool) -> Result<Self> {
+        let path = Context::cache_file_path()?;
+        let json = match tokio::fs::read_to_string(&path).await {
+            Ok(json) => json,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Self::new()),
+            Err(err) => {
+                return Err(err).with_context(|| format!("Failed to read {}", path.display()))
+            }
+        };
+
+        let parsed = serde_json::from_str::<Value>(&json).map_err(anyhow::Error::from);
+        let version = match &parsed {
+            Ok(value) => value.get("version").and_then(Value::as_u64).unwrap_or(0),
+            Err(_) => 0,
+        };
+        if version > CACHE_VERSION {
+            bail!(
+                "{} was written by a newer petit (cache version {}). \
+                 Upgrade petit or run \"petit cache clear --all\"",
+                path.display(),
+                version
+            );
+        }
+
+        match parsed.and_then(Self::from_value) {
+            Ok(mut file) => {
+                file.count_hits().await?;
+                Ok(file)
+            }
+            Err(_) if !locked => Ok(Self::new()),
+            Err(err) => {
+                let broken = path.with_extension("json.broken");
+                tokio::fs::rename(&path, &broken).await?;
+                eprintln!(
+                    "The cache could not be read ({}) and was moved to {}",
+                    err,
+                    broken.display()
+                );
+                Ok(Self::new())
+            }
+        }
+    }
+
+    async fn write(&self) -> Result<()> {
+        let data = serde_json::to_string(self)?;
+        write_private(&Context::cache_file_path()?, data.as_bytes()).await?;
+        remove_if_exists(&hits_path()?).await
+    }
+
+    fn new() -> Self {
+        Self {
+            version: CACHE_VERSION,
+            accounts: BTreeMap::new(),
+        }
+    }
+
+    /// Adds the hits logged since the cache file was last written.
+    async fn count_hits(&mut self) -> Result<()> {
+        let path = hits_path()?;
+        let log = match tokio::fs::read_to_string(&path).await {
+            Ok(log) => log,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(err) => {
+                return Err(err).with_context(|| format!("Failed to read {}", path.display()))
+            }
+        };
+        // A line cut short by a crash is not a hit.
+        let hits = log
+            .lines()
+            .filter_map(|x| serde_json::from_str::<(String, String)>(x).ok());
+        for (account, key) in hits {
+            if let Some(entry) = self
+                .accounts
+                .get_mut(&account)
+                .and_then(|x| x.get_mut(&key))
+            {
+                entry.hits += 1;
+            }
+        }
+        Ok(())
+    }
+
+    fn from_value(value: Value) -> Result<Self> {
+        let (account, entries) = match value.get("version").and_then(Value::as_u64) {
+            Some(CACHE_VERSION) => return Ok(serde_json::from_value(value)?),
+            Some(_) => {
+                let v1: CacheFileV1 = serde_json::from_value(value)?;
+                (v1.account, v1.entries)
+            }
+            None => match serde_json::from_value(value)? {
+                UnversionedCache::Entries { account, entries } => (account, entries),
+                UnversionedCache::Legacy {
+                    account,
+                    latest_call,
+                    timeline,
+                } => {
+                    let mut entries = BTreeMap::new();
+                    entries.insert(
+                        HOME.to_string(),
+                        CacheEntry {
+                            latest_call,
+                            timeline,
+                            ..Default::default()
+                        },
+                    );
+                    // Before profiles, the single token became the default profile.
+                    (
+                        account.or_else(|| Some(DEFAULT_PROFILE.to_string())),
+                        entries,
+                    )
+                }
+            },
+        };
+        let mut file = Self::new();
+        // Other caches of older versions that were not tied to an account are dropped.
+        if let Some(account) = account {
+            file.accounts.insert(account, entries);
+        }
+        Ok(file)
+    }
+}
+
+/// Hits are appended here instead of rewriting the cache file for every one,
+/// and folded into the cache file on its next write.
+fn hits_path() -> Result<PathBuf> {
+    Ok(Context::cache_file_path()?.with_extension("hits"))
+}
+
+fn lock_path() -> Result<PathBuf> {
+    Ok(Context::cache_file_path()?.with_extension("lock"))
+}
+
+impl CacheEntry {
+    /// Whether the entry was fetched less than `ttl` seconds ago.
+    pub fn is_fresh(&self, ttl: i64) -> bool {
+        self.latest_call
+            .map(|x| (Utc::now() - x).num_seconds() < ttl)
+            .unwrap_or(false)
+    }
+
+    pub fn newest_id(&self) -> Option<u64> {
+        self.timeline.iter().map(|x| x.id).max()
+    }
+
+    /// Adds `tweets` to the timeline, replacing cached copies of the same
+    /// tweet, and keeps the newest `limit` tweets.
+    pub fn merge(&mut self, tweets: Vec<TrimTweet>, limit: usize) {
+        let mut ids = HashSet::new();
+        let mut timeline = tweets
+            .into_iter()
+            .chain(self.timeline.drain(..))
+            .filter(|x| ids.insert(x.id))
+            .collect::<Vec<_>>();
+        timeline.sort_by_key(|x| std::cmp::Reverse(x.id));
+        timeline.truncate(limit);
+        self.timeline = timeline;
+    }
+}
+
+async fn remove_if_exists(path: &std::path::Path) -> Result<()> {
+    match tokio::fs::remove_file(path).await {
+        Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+            Err(err).with_context(|| format!("Failed to remove {}", path.display()))
+        }
+        _ => Ok(()),
+    }
+}
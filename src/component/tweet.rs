@@ -1,8 +1,10 @@
-use crate::component::Component;
+use crate::component::ComponentWithContext;
+use crate::config::{Config, Theme, TimeConfig};
+use chrono::{DateTime, Utc};
 use kuon::{TrimTweet, Tweet};
 use tui::{
     layout::Rect,
-    style::{Color, Style},
+    style::Style,
     text::{Span, Spans},
     widgets::{List, ListItem},
 };
@@ -15,6 +17,8 @@ pub struct TweetView {
     pub tweet: String,
     pub retweet_count: u64,
     pub favorite_count: u64,
+    /// When the shown tweet was posted; for a retweet, that is the original tweet.
+    pub created_at: Option<DateTime<Utc>>,
 }
 
 impl From<&Tweet> for TweetView {
@@ -29,6 +33,7 @@ impl From<&Tweet> for TweetView {
                 retweet_user_name: Some(x.user.screen_name.clone()),
                 retweet_count: retweet.retweet_count,
                 favorite_count: retweet.favorite_count,
+                created_at: Some(retweet.created_at),
             })
             .unwrap_or(Self {
                 id: x.id_str.clone().unwrap(),
@@ -38,6 +43,7 @@ impl From<&Tweet> for TweetView {
                 retweet_user_name: None,
                 retweet_count: x.retweet_count,
                 favorite_count: x.favorite_count,
+                created_at: Some(x.created_at),
             })
     }
 }
@@ -62,6 +68,7 @@ impl From<&TrimTweet> for TweetView {
                 retweet_user_name: x.user.screen_name.clone(),
                 retweet_count: retweet.retweet_count,
                 favorite_count: retweet.favorite_count,
+                created_at: retweet.created_at,
             })
             .unwrap_or(TweetView {
                 id: x.id_str.clone().unwrap(),
@@ -71,25 +78,35 @@ impl From<&TrimTweet> for TweetView {
                 retweet_user_name: None,
                 retweet_count: x.retweet_count,
                 favorite_count: x.favorite_count,
+                created_at: x.created_at,
             })
     }
 }
 
 impl<'a> TweetView {
-    fn to_list_item(&self, width: usize) -> ListItem<'a> {
+    fn to_list_item(
+        &self,
+        width: usize,
+        theme: &Theme,
+        time: &TimeConfig,
+        now: DateTime<Utc>,
+    ) -> ListItem<'a> {
         let tweet_user = vec![Spans::from(vec![
+            Span::styled(self.user_name.clone(), Style::default().fg(theme.user_name)),
             Span::styled(
-                self.user_name.clone(),
-                Style::default().fg(Color::Rgb(255, 128, 0)),
+                format!("@{}", self.screen_name),
+                Style::default().fg(theme.screen_name),
             ),
             Span::styled(
-                format!("@{}", self.screen_name),
-                Style::default().fg(Color::DarkGray),
+                self.created_at
+                    .map(|x| format!(" · {}", time.show(x, now)))
+                    .unwrap_or_default(),
+                Style::default().fg(theme.screen_name),
             ),
             if let Some(retweet_user) = self.retweet_user_name.clone() {
                 Span::styled(
                     format!(" 🔁 {} Retweeted", retweet_user),
-                    Style::default().fg(Color::LightGreen),
+                    Style::default().fg(theme.retweet),
                 )
             } else {
                 Span::raw("")
@@ -102,12 +119,12 @@ impl<'a> TweetView {
         let tweet_info = vec![Spans::from(vec![
             Span::styled(
                 format!("🔁 {}", self.retweet_count),
-                Style::default().fg(Color::LightGreen),
+                Style::default().fg(theme.retweet),
             ),
             Span::raw(" "),
             Span::styled(
                 format!("❤️ {}", self.favorite_count),
-                Style::default().fg(Color::LightRed),
+                Style::default().fg(theme.favorite),
             ),
         ])];
         let margin = vec![Spans::default()];
@@ -116,23 +133,25 @@ impl<'a> TweetView {
     }
 }
 
-impl<'a> Component<List<'a>> for Vec<TrimTweet> {
-    fn view(&self, area: &Rect) -> List<'a> {
+impl<'a> ComponentWithContext<List<'a>, &Config> for Vec<TrimTweet> {
+    fn view(&self, area: &Rect, config: &Config) -> List<'a> {
+        let now = Utc::now();
         let tweets = self
             .iter()
-            .map(|x| TweetView::from(x))
-            .map(|x| x.to_list_item(area.width as usize))
+            .map(TweetView::from)
+            .map(|x| x.to_list_item(area.width as usize, &config.theme, &config.time, now))
             .collect::<Vec<_>>();
         List::new(tweets)
     }
 }
 
-impl<'a> Component<List<'a>> for Vec<Tweet> {
-    fn view(&self, area: &Rect) -> List<'a> {
+impl<'a> ComponentWithContext<List<'a>, &Config> for Vec<Tweet> {
+    fn view(&self, area: &Rect, config: &Config) -> List<'a> {
+        let now = Utc::now();
         let tweets = self
             .iter()
-            .map(|x| TweetView::from(x))
-            .map(|x| x.to_list_item(area.width as usize))
+            .map(TweetView::from)
+            .map(|x| x.to_list_item(area.width as usize, &config.theme, &config.time, now))
             .collect::<Vec<_>>();
         List::new(tweets)
     }
@@ -0,0 +1,390 @@
+use anyhow::{bail, Context as _, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use termion::event::Key;
+use tokio::fs::File;
+use tokio::io::BufReader;
+use tui::style::Color;
+
+use crate::context::Context;
+use crate::display::DisplayType;
+use crate::utils::file::write_private;
+use crate::utils::stdio::read;
+use crate::utils::time::{self, Zone};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Config {
+    pub api: ApiConfig,
+    pub timeline: TimelineConfig,
+    pub time: TimeConfig,
+    pub cache: CacheConfig,
+    /// Named output templates for `--format`.
+    pub templates: BTreeMap<String, String>,
+    pub ui: UiConfig,
+    pub theme: Theme,
+    pub keys: KeyBindings,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct ApiConfig {
+    pub key: Option<String>,
+    pub secret: Option<String>,
+    /// Where API requests go. Point it at a mock server to test without Twitter.
+    pub base_url: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct TimelineConfig {
+    pub display: DisplayType,
+    pub count: u64,
+}
+
+impl Default for TimelineConfig {
+    fn default() -> Self {
+        Self {
+            display: DisplayType::Standard,
+            count: 30,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct TimeConfig {
+    /// "local", "utc" or an offset such as "+09:00".
+    #[serde(with = "zone")]
+    pub timezone: Zone,
+    /// strftime format of absolute times.
+    #[serde(with = "strftime")]
+    pub format: String,
+    /// Show tweets of the last day as "3m" or "2h" instead of the time they were posted.
+    pub relative: bool,
+}
+
+impl Default for TimeConfig {
+    fn default() -> Self {
+        Self {
+            timezone: Zone::Local,
+            format: String::from("%Y-%m-%d %H:%M"),
+            relative: true,
+        }
+    }
+}
+
+impl TimeConfig {
+    pub fn absolute(&self, time: DateTime<Utc>) -> String {
+        self.timezone.format(time, &self.format)
+    }
+
+    /// The relative time for recent tweets, otherwise the absolute one.
+    pub fn show(&self, time: DateTime<Utc>, now: DateTime<Utc>) -> String {
+        let elapsed = now - time;
+        if self.relative && elapsed < chrono::Duration::days(1) {
+            time::relative(elapsed)
+        } else {
+            self.absolute(time)
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct CacheConfig {
+    /// Seconds a cached response is served without calling the API.
+    pub ttl: i64,
+    /// Overrides `ttl` for single endpoints such as `home` or `search`.
+    pub endpoint_ttl: BTreeMap<String, i64>,
+    /// Maximum number of tweets kept per cached endpoint.
+    pub limit: usize,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            ttl: 60,
+            endpoint_ttl: BTreeMap::new(),
+            limit: 200,
+        }
+    }
+}
+
+impl CacheConfig {
+    pub fn ttl(&self, endpoint: &str) -> i64 {
+        self.endpoint_ttl.get(endpoint).copied().unwrap_or(self.ttl)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct UiConfig {
+    /// Milliseconds between redraws of the TUI.
+    pub tick_rate: u64,
+}
+
+impl Default for UiConfig {
+    fn default() -> Self {
+        Self { tick_rate: 1000 }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Theme {
+    #[serde(with = "color")]
+    pub user_name: Color,
+    #[serde(with = "color")]
+    pub screen_name: Color,
+    #[serde(with = "color")]
+    pub retweet: Color,
+    #[serde(with = "color")]
+    pub favorite: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            user_name: Color::Rgb(255, 128, 0),
+            screen_name: Color::DarkGray,
+            retweet: Color::LightGreen,
+            favorite: Color::LightRed,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct KeyBindings {
+    #[serde(with = "key")]
+    pub quit: Key,
+    #[serde(with = "key")]
+    pub search: Key,
+    #[serde(with = "key")]
+    pub delete: Key,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            quit: Key::Esc,
+            search: Key::Char('\n'),
+            delete: Key::Backspace,
+        }
+    }
+}
+
+impl Config {
+    pub async fn load() -> Result<Self> {
+        let path = Context::config_file_path()?;
+        let file = match File::open(&path).await {
+            Ok(file) => file,
+            Err(_) => return Ok(Self::default()),
+        };
+        let mut reader = BufReader::new(file);
+        let text = read(&mut reader).await?;
+
+        toml::from_str(&text).with_context(|| format!("Invalid config file: {}", path.display()))
+    }
+
+    /// Reads the config file as a plain table so that `petit config set`
+    /// only rewrites what the user changed.
+    pub async fn load_table() -> Result<toml::Value> {
+        let path = Context::config_file_path()?;
+        let file = match File::open(&path).await {
+            Ok(file) => file,
+            Err(_) => return Ok(toml::Value::Table(Default::default())),
+        };
+        let mut reader = BufReader::new(file);
+        let text = read(&mut reader).await?;
+
+        text.parse()
+            .with_context(|| format!("Invalid config file: {}", path.display()))
+    }
+
+    pub async fn save_table(table: &toml::Value) -> Result<()> {
+        // Make sure the table is still a valid config before writing it.
+        Config::deserialize(table.clone())?;
+
+        let text = toml::to_string_pretty(table)?;
+        write_private(&Context::config_file_path()?, text.as_bytes()).await
+    }
+}
+
+pub fn parse_color(s: &str) -> Result<Color> {
+    let color = match s.to_lowercase().as_str() {
+        "reset" => Color::Reset,
+        "black" => Color::Black,
+        "red" => Color::Red,
+        "green" => Color::Green,
+        "yellow" => Color::Yellow,
+        "blue" => Color::Blue,
+        "magenta" => Color::Magenta,
+        "cyan" => Color::Cyan,
+        "gray" => Color::Gray,
+        "darkgray" => Color::DarkGray,
+        "lightred" => Color::LightRed,
+        "lightgreen" => Color::LightGreen,
+        "lightyellow" => Color::LightYellow,
+        "lightblue" => Color::LightBlue,
+        "lightmagenta" => Color::LightMagenta,
+        "lightcyan" => Color::LightCyan,
+        "white" => Color::White,
+        hex if hex.starts_with('#') && hex.len() == 7 => {
+            let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16);
+            Color::Rgb(channel(1)?, channel(3)?, channel(5)?)
+        }
+        index => match index.parse::<u8>() {
+            Ok(n) => Color::Indexed(n),
+            Err(_) => bail!("unknown color: {}", s),
+        },
+    };
+    Ok(color)
+}
+
+pub fn color_to_string(color: &Color) -> String {
+    match color {
+        Color::Reset => String::from("reset"),
+        Color::Black => String::from("black"),
+        Color::Red => String::from("red"),
+        Color::Green => String::from("green"),
+        Color::Yellow => String::from("yellow"),
+        Color::Blue => String::from("blue"),
+        Color::Magenta => String::from("magenta"),
+        Color::Cyan => String::from("cyan"),
+        Color::Gray => String::from("gray"),
+        Color::DarkGray => String::from("darkgray"),
+        Color::LightRed => String::from("lightred"),
+        Color::LightGreen => String::from("lightgreen"),
+        Color::LightYellow => String::from("lightyellow"),
+        Color::LightBlue => String::from("lightblue"),
+        Color::LightMagenta => String::from("lightmagenta"),
+        Color::LightCyan => String::from("lightcyan"),
+        Color::White => String::from("white"),
+        Color::Rgb(r, g, b) => format!("#{:02x}{:02x}{:02x}", r, g, b),
+        Color::Indexed(n) => n.to_string(),
+    }
+}
+
+pub fn parse_key(s: &str) -> Result<Key> {
+    let lower = s.to_lowercase();
+    let key = match lower.as_str() {
+        "esc" => Key::Esc,
+        "enter" => Key::Char('\n'),
+        "tab" => Key::Char('\t'),
+        "backspace" => Key::Backspace,
+        "delete" => Key::Delete,
+        "up" => Key::Up,
+        "down" => Key::Down,
+        "left" => Key::Left,
+        "right" => Key::Right,
+        "home" => Key::Home,
+        "end" => Key::End,
+        "pageup" => Key::PageUp,
+        "pagedown" => Key::PageDown,
+        _ => {
+            if let Some(c) = single_char(s) {
+                Key::Char(c)
+            } else if let Some(c) = lower.strip_prefix("ctrl-").and_then(single_char) {
+                Key::Ctrl(c)
+            } else if let Some(c) = lower.strip_prefix("alt-").and_then(single_char) {
+                Key::Alt(c)
+            } else {
+                bail!("unknown key: {}", s)
+            }
+        }
+    };
+    Ok(key)
+}
+
+fn single_char(s: &str) -> Option<char> {
+    let mut chars = s.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Some(c),
+        _ => None,
+    }
+}
+
+pub fn key_to_string(key: &Key) -> String {
+    match key {
+        Key::Esc => String::from("esc"),
+        Key::Char('\n') => String::from("enter"),
+        Key::Char('\t') => String::from("tab"),
+        Key::Backspace => String::from("backspace"),
+        Key::Delete => String::from("delete"),
+        Key::Up => String::from("up"),
+        Key::Down => String::from("down"),
+        Key::Left => String::from("left"),
+        Key::Right => String::from("right"),
+        Key::Home => String::from("home"),
+        Key::End => String::from("end"),
+        Key::PageUp => String::from("pageup"),
+        Key::PageDown => String::from("pagedown"),
+        Key::Char(c) => c.to_string(),
+        Key::Ctrl(c) => format!("ctrl-{}", c),
+        Key::Alt(c) => format!("alt-{}", c),
+        other => format!("{:?}", other).to_lowercase(),
+    }
+}
+
+mod color {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+    use tui::style::Color;
+
+    pub fn serialize<S: Serializer>(color: &Color, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&super::color_to_string(color))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        super::parse_color(&s).map_err(de::Error::custom)
+    }
+}
+
+mod zone {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    use crate::utils::time::Zone;
+
+    pub fn serialize<S: Serializer>(zone: &Zone, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&zone.to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Zone, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(de::Error::custom)
+    }
+}
+
+mod strftime {
+    use chrono::format::{Item, StrftimeItems};
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(format: &str, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(format)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        if StrftimeItems::new(&s).any(|x| x == Item::Error) {
+            return Err(de::Error::custom(format!("invalid time format: {}", s)));
+        }
+        Ok(s)
+    }
+}
+
+mod key {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+    use termion::event::Key;
+
+    pub fn serialize<S: Serializer>(key: &Key, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&super::key_to_string(key))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Key, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        super::parse_key(&s).map_err(de::Error::custom)
+    }
+}
@@ -1,102 +1,164 @@
-use anyhow::Result;
-use chrono::{DateTime, Utc};
-use kuon::{OAuthToken, TrimTweet, TwitterAPI};
-use serde::{Deserialize, Serialize};
+use anyhow::{anyhow, bail, Context as _, Result};
+use chrono::Utc;
+use kuon::OAuthToken;
 use std::path::PathBuf;
-use tokio::fs::File;
-use tokio::io::{AsyncWriteExt, BufReader, BufWriter};
 
-use crate::utils::stdio::read;
+use crate::account::Accounts;
+use crate::api::{self, Client};
+use crate::application::Application;
+use crate::cache::Cache;
+use crate::config::Config;
+use crate::mute::{MuteFilter, Mutes};
+use crate::sub_command::SubCommand;
+use crate::utils::dirs;
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct Cache {
-    pub latest_call: Option<DateTime<Utc>>,
-    pub timeline: Vec<TrimTweet>,
-    pub count: i32,
+#[derive(Debug, Clone)]
+pub struct Credentials {
+    pub api_key: String,
+    pub api_secret: String,
 }
 
 pub struct Context {
-    pub client: Option<kuon::TwitterAPI>,
-    pub api_key: String,
-    pub api_secret: String,
+    pub client: Option<Client>,
+    pub credentials: Option<Credentials>,
+    pub base_url: String,
+    pub config: Config,
     pub cache: Option<Cache>,
+    pub accounts: Accounts,
+    pub account: Option<String>,
+    pub offline: bool,
+    /// Applied to every tweet list before it is shown, once loaded by `load_mutes`.
+    pub mutes: MuteFilter,
 }
 
 impl Context {
-    pub async fn new() -> Result<Self> {
-        let api_key = option_env!("API_KEY").unwrap();
-        let api_secret = option_env!("API_SECRET_KEY").unwrap();
+    pub async fn new(app: &Application) -> Result<Self> {
+        dirs::prepare(&Self::oauth_token_path()?, &Self::cache_file_path()?).await?;
+
+        let config = Config::load().await?;
+        let credentials = Self::resolve_credentials(app, &config);
+
+        let account = app.account.clone();
+        let accounts = Accounts::load().await?;
+        let (account, oauth) = match accounts.get(account.as_deref()) {
+            // `login` saves the profile named by --account, whether it exists or not.
+            _ if matches!(app.subcmd, SubCommand::Login(_)) => (account, None),
+            Some((name, oauth)) => (Some(name.to_string()), Some(oauth.clone())),
+            None => match account {
+                Some(name) => bail!("No such account: {}. run \"petit account list\"", name),
+                None => (None, None),
+            },
+        };
 
-        let client = Self::build_client(api_key, api_secret).await;
-        let cache = Self::get_cache().await;
+        let base_url = std::env::var("PETIT_API_BASE_URL")
+            .ok()
+            .filter(|x| !x.is_empty())
+            .or_else(|| config.api.base_url.clone())
+            .unwrap_or_else(|| api::DEFAULT_BASE_URL.to_string());
+        let client = match (&credentials, oauth) {
+            (Some(credentials), Some(oauth)) => {
+                Some(Client::new(credentials.clone(), oauth, &base_url))
+            }
+            _ => None,
+        };
+        let cache = Cache::load(account.as_deref()).await.unwrap_or_else(|err| {
+            eprintln!("{:#}", err);
+            None
+        });
 
         Ok(Self {
             client,
-            api_key: api_key.to_string(),
-            api_secret: api_secret.to_string(),
+            credentials,
+            base_url,
+            config,
             cache,
+            accounts,
+            account,
+            offline: app.offline,
+            mutes: MuteFilter::default(),
         })
     }
 
-    pub fn oauth_token_path() -> PathBuf {
-        let home_dir = std::env::var("HOME").unwrap();
-        let path = {
-            let mut path = PathBuf::new();
-            path.push(home_dir);
-            path.push(".petit");
-            path
-        };
-        path
+    /// Loads the mute rules, only in commands that list tweets, so that a broken
+    /// mutes file cannot keep `petit mute` from fixing it.
+    pub async fn load_mutes(&mut self) -> Result<()> {
+        self.mutes = Mutes::load().await?.filter(Utc::now());
+        Ok(())
     }
 
-    async fn get_oauth_token() -> Option<OAuthToken> {
-        let file = File::open(Self::oauth_token_path()).await.ok()?;
-        let mut reader = BufReader::new(file);
-        let json = read(&mut reader).await.ok()?;
-        serde_json::from_str(&json).ok()?
+    pub fn credentials(&self) -> Result<&Credentials> {
+        self.credentials.as_ref().with_context(|| {
+            format!(
+                "API key and secret are not set. Pass --api-key and --api-secret, \
+                 set PETIT_API_KEY and PETIT_API_SECRET, \
+                 or add key and secret to the [api] section of {}",
+                Self::config_file_path()
+                    .map(|x| x.display().to_string())
+                    .unwrap_or_default()
+            )
+        })
     }
 
-    fn cache_file_path() -> PathBuf {
-        let home_dir = std::env::var("HOME").unwrap();
-        let path = {
-            let mut path = PathBuf::new();
-            path.push(home_dir);
-            path.push(".cache");
-            path.push("petit");
-            path
-        };
-        path
+    pub fn client(&self) -> Result<&Client> {
+        if self.offline {
+            bail!("This command needs the network and cannot run with --offline");
+        }
+        self.credentials()?;
+        self.client
+            .as_ref()
+            .ok_or_else(|| anyhow!("Please login. run \"petit login\""))
     }
 
-    async fn get_cache() -> Option<Cache> {
-        let file = File::open(Self::cache_file_path()).await.ok()?;
-        let mut reader = BufReader::new(file);
-        let json = read(&mut reader).await.ok()?;
-        serde_json::from_str(&json).ok()?
+    /// Builds a client for a token that is not stored yet, such as one being logged in.
+    pub fn build_client(&self, oauth: OAuthToken) -> Result<Client> {
+        Ok(Client::new(
+            self.credentials()?.clone(),
+            oauth,
+            &self.base_url,
+        ))
     }
 
-    pub async fn save_cache(cache: &Cache) -> Result<()> {
-        let file = File::create(Context::cache_file_path()).await?;
-        let mut writer = BufWriter::new(file);
+    /// Picks the API key and secret from, in order, command line flags or
+    /// environment variables, the config file and the values baked in at build time.
+    fn resolve_credentials(app: &Application, config: &Config) -> Option<Credentials> {
+        let api_key = app
+            .api_key
+            .clone()
+            .or_else(|| config.api.key.clone())
+            .or_else(|| option_env!("API_KEY").map(String::from))?;
+        let api_secret = app
+            .api_secret
+            .clone()
+            .or_else(|| config.api.secret.clone())
+            .or_else(|| option_env!("API_SECRET_KEY").map(String::from))?;
 
-        let data = serde_json::to_string(cache)?;
-        writer.write_all(data.as_bytes()).await?;
-        writer.flush().await?;
+        Some(Credentials {
+            api_key,
+            api_secret,
+        })
+    }
 
-        Ok(())
+    pub fn config_file_path() -> Result<PathBuf> {
+        Ok(dirs::config_dir()?.join("config.toml"))
+    }
+
+    pub fn mutes_file_path() -> Result<PathBuf> {
+        Ok(dirs::config_dir()?.join("mutes.toml"))
+    }
+
+    pub fn oauth_token_path() -> Result<PathBuf> {
+        Ok(dirs::data_dir()?.join("accounts.json"))
+    }
+
+    pub fn outbox_path() -> Result<PathBuf> {
+        Ok(dirs::data_dir()?.join("outbox.json"))
+    }
+
+    pub fn archive_path() -> Result<PathBuf> {
+        Ok(dirs::data_dir()?.join("archive.db"))
     }
 
-    async fn build_client(api_key: &str, api_secret: &str) -> Option<kuon::TwitterAPI> {
-        let oauth = Self::get_oauth_token().await?;
-        let client = TwitterAPI::builder()
-            .api_key(api_key)
-            .api_secret_key(api_secret)
-            .access_token(oauth.token)
-            .access_token_secret(oauth.secret)
-            .build()
-            .await
-            .ok()?;
-
-        Some(client)
+    pub fn cache_file_path() -> Result<PathBuf> {
+        Ok(dirs::cache_dir()?.join("cache.json"))
     }
 }
@@ -0,0 +1,301 @@
+use anyhow::{Context as _, Result};
+use chrono::{DateTime, Local, Utc};
+use clap::Clap;
+use colored::Colorize;
+use kuon::TrimTweet;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::str::FromStr;
+use tokio::io::{AsyncWriteExt, BufWriter, Stdout};
+
+use crate::component::tweet::TweetView;
+use crate::config::{Config, TimeConfig};
+use crate::schema::TweetRecord;
+use crate::template::{self, Template};
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum DisplayType {
+    #[default]
+    Standard,
+    Json,
+    Ndjson,
+    Csv,
+    Tsv,
+}
+
+impl DisplayType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Standard => "standard",
+            Self::Json => "json",
+            Self::Ndjson => "ndjson",
+            Self::Csv => "csv",
+            Self::Tsv => "tsv",
+        }
+    }
+}
+
+impl FromStr for DisplayType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "standard" => Ok(Self::Standard),
+            "json" => Ok(Self::Json),
+            "ndjson" => Ok(Self::Ndjson),
+            "csv" => Ok(Self::Csv),
+            "tsv" => Ok(Self::Tsv),
+            _ => Err(String::from("no match string")),
+        }
+    }
+}
+
+impl Serialize for DisplayType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for DisplayType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse()
+            .map_err(|_| de::Error::custom(format!("unknown display type: {}", s)))
+    }
+}
+
+/// How tweet listings are written, and how their times are shown.
+#[derive(Debug, Clone)]
+pub(crate) struct Format {
+    layout: Layout,
+    time: TimeConfig,
+}
+
+/// A built-in display type or a user template.
+#[derive(Debug, Clone)]
+enum Layout {
+    Display(DisplayType),
+    Template(Template),
+}
+
+/// The output options shared by every command that lists tweets.
+#[derive(Debug, Clap)]
+pub(crate) struct OutputOpts {
+    #[clap(long, short)]
+    display: Option<DisplayType>,
+    /// Template such as '{created_at:%H:%M} @{screen_name}: {text}', or the name of one in [templates]
+    #[clap(long, conflicts_with = "display")]
+    format: Option<String>,
+}
+
+impl OutputOpts {
+    pub fn is_set(&self) -> bool {
+        self.display.is_some() || self.format.is_some()
+    }
+
+    pub fn format(&self, config: &Config) -> Result<Format> {
+        let layout = match &self.format {
+            Some(format) => {
+                let template = config.templates.get(format).unwrap_or(format);
+                Layout::Template(template.parse()?)
+            }
+            None => Layout::Display(self.display.unwrap_or(config.timeline.display)),
+        };
+        Ok(Format {
+            layout,
+            time: config.time.clone(),
+        })
+    }
+}
+
+/// Writes tweets to `stdout` in the given format.
+pub(crate) async fn output(
+    stdout: &mut BufWriter<Stdout>,
+    tweet_list: &[TrimTweet],
+    format: &Format,
+) -> Result<()> {
+    let mut writer = TweetWriter::begin(stdout, format).await?;
+    writer.write(tweet_list).await?;
+    writer.finish().await
+}
+
+/// Writes tweets batch by batch as they arrive, so that long listings are streamed.
+pub(crate) struct TweetWriter<'a> {
+    stdout: &'a mut BufWriter<Stdout>,
+    format: &'a Format,
+    written: usize,
+}
+
+impl<'a> TweetWriter<'a> {
+    pub async fn begin(stdout: &'a mut BufWriter<Stdout>, format: &'a Format) -> Result<Self> {
+        match &format.layout {
+            Layout::Display(DisplayType::Json) => stdout.write_all(b"[").await?,
+            Layout::Display(display @ (DisplayType::Csv | DisplayType::Tsv)) => {
+                let mut writer = table_writer(*display);
+                writer.write_record(Row::COLUMNS)?;
+                stdout.write_all(&writer.into_inner()?).await?;
+            }
+            Layout::Display(DisplayType::Standard | DisplayType::Ndjson) | Layout::Template(_) => {}
+        }
+        Ok(Self {
+            stdout,
+            format,
+            written: 0,
+        })
+    }
+
+    pub async fn write(&mut self, tweet_list: &[TrimTweet]) -> Result<()> {
+        let time = &self.format.time;
+        let display = match &self.format.layout {
+            Layout::Display(display) => *display,
+            Layout::Template(template) => {
+                for tweet in tweet_list {
+                    let line = template.render(tweet, time) + "\n";
+                    self.stdout.write_all(line.as_bytes()).await?;
+                }
+                self.written += tweet_list.len();
+                return self.stdout.flush().await.with_context(|| "Output Error");
+            }
+        };
+        match display {
+            DisplayType::Standard => {
+                let now = Utc::now();
+                let output_line = tweet_list.iter().map(TweetView::from).map(|x| {
+                    format!(
+                        "{} {} {} {}\n {}\n\n",
+                        x.user_name,
+                        format!("@{}", x.screen_name).bright_red(),
+                        x.created_at
+                            .map(|x| time.show(x, now))
+                            .unwrap_or_default()
+                            .dimmed(),
+                        x.retweet_user_name
+                            .map(|x| format!("RT:@{}", x))
+                            .unwrap_or_default()
+                            .bright_green(),
+                        x.tweet,
+                    )
+                });
+                for line in output_line {
+                    self.stdout.write_all(line.as_bytes()).await?;
+                }
+            }
+            DisplayType::Json => {
+                for (i, tweet) in tweet_list.iter().enumerate() {
+                    if self.written + i > 0 {
+                        self.stdout.write_all(b",").await?;
+                    }
+                    let json = serde_json::to_string(tweet)?;
+                    self.stdout.write_all(json.as_bytes()).await?;
+                }
+            }
+            DisplayType::Ndjson => {
+                for tweet in tweet_list {
+                    let json = serde_json::to_string(&TweetRecord::from(tweet))? + "\n";
+                    self.stdout.write_all(json.as_bytes()).await?;
+                }
+            }
+            DisplayType::Csv | DisplayType::Tsv => {
+                let mut writer = table_writer(display);
+                for tweet in tweet_list {
+                    writer.serialize(Row::new(tweet, display, time))?;
+                }
+                self.stdout.write_all(&writer.into_inner()?).await?;
+            }
+        }
+        self.written += tweet_list.len();
+
+        self.stdout.flush().await.with_context(|| "Output Error")
+    }
+
+    pub async fn finish(self) -> Result<()> {
+        if let Layout::Display(DisplayType::Json) = self.format.layout {
+            self.stdout.write_all(b"]").await?;
+        }
+        self.stdout.flush().await.with_context(|| "Output Error")
+    }
+}
+
+/// One line of CSV or TSV output. The columns are fixed so that every row lines up.
+#[derive(Serialize)]
+struct Row {
+    id: String,
+    created_at: String,
+    user: String,
+    screen_name: String,
+    text: String,
+    retweeted_by: String,
+    retweet_count: u64,
+    favorite_count: u64,
+    url: String,
+}
+
+impl Row {
+    const COLUMNS: [&'static str; 9] = [
+        "id",
+        "created_at",
+        "user",
+        "screen_name",
+        "text",
+        "retweeted_by",
+        "retweet_count",
+        "favorite_count",
+        "url",
+    ];
+
+    fn new(tweet: &TrimTweet, display: DisplayType, time: &TimeConfig) -> Self {
+        let view = TweetView::from(tweet);
+        // TSV has no quoting, so characters that would break the row are replaced.
+        let field = |x: String| match display {
+            DisplayType::Tsv => x.replace(&['\t', '\r', '\n'][..], " "),
+            _ => x,
+        };
+        let url = template::tweet_url(&view);
+
+        Self {
+            id: view.id,
+            created_at: view
+                .created_at
+                .map(|x| time.timezone.rfc3339(x))
+                .unwrap_or_default(),
+            user: field(view.user_name),
+            screen_name: field(view.screen_name),
+            text: field(view.tweet),
+            retweeted_by: field(view.retweet_user_name.unwrap_or_default()),
+            retweet_count: view.retweet_count,
+            favorite_count: view.favorite_count,
+            url,
+        }
+    }
+}
+
+/// CSV follows RFC 4180: fields are quoted as needed and lines end with CRLF.
+fn table_writer(display: DisplayType) -> csv::Writer<Vec<u8>> {
+    let mut builder = csv::WriterBuilder::new();
+    builder.has_headers(false);
+    if display == DisplayType::Tsv {
+        builder.delimiter(b'\t').quote_style(csv::QuoteStyle::Never);
+    } else {
+        builder.terminator(csv::Terminator::CRLF);
+    }
+    builder.from_writer(Vec::new())
+}
+
+/// Whether writing failed because the reader of stdout went away, as with `petit tl | head`.
+pub(crate) fn is_broken_pipe(err: &anyhow::Error) -> bool {
+    err.chain().any(|x| {
+        x.downcast_ref::<std::io::Error>()
+            .map(|x| x.kind() == std::io::ErrorKind::BrokenPipe)
+            .unwrap_or(false)
+    })
+}
+
+/// Tells that the shown data comes from local storage and when it was fetched.
+pub(crate) fn stale_notice(fetched_at: Option<DateTime<Utc>>) -> String {
+    match fetched_at {
+        Some(x) => format!(
+            "Offline: showing data fetched at {}",
+            x.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S")
+        ),
+        None => String::from("Offline: showing stored data"),
+    }
+}
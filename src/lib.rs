@@ -1,5 +1,15 @@
+pub mod account;
+mod api;
 pub mod application;
+pub mod archive;
+pub mod cache;
 mod component;
+pub mod config;
 pub mod context;
+pub mod display;
+pub mod mute;
+pub mod outbox;
+pub mod schema;
 mod sub_command;
+pub mod template;
 pub mod utils;
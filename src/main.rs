@@ -1,11 +1,20 @@
 use anyhow::Result;
 use clap::Clap;
-use petit::{application::Application, context::Context};
+use petit::{application::Application, context::Context, utils::color};
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() {
     let app = Application::parse();
-    let ctx = Context::new().await?;
+    app.color.apply();
+
+    if let Err(err) = run(app).await {
+        eprintln!("{} {:?}", color::error("Error:"), err);
+        std::process::exit(1);
+    }
+}
+
+async fn run(app: Application) -> Result<()> {
+    let ctx = Context::new(&app).await?;
 
     app.run(ctx).await?;
     Ok(())
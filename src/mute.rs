@@ -0,0 +1,190 @@
+use anyhow::{bail, Context as _, Result};
+use chrono::{DateTime, Utc};
+use kuon::TrimTweet;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::component::tweet::TweetView;
+use crate::context::Context;
+use crate::utils::file::write_private;
+
+/// Mute rules, kept in `mutes.toml` next to the config.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Mutes {
+    #[serde(default)]
+    pub rules: Vec<MuteRule>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MuteRule {
+    pub kind: MuteKind,
+    pub value: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum MuteKind {
+    /// Tweets whose text contains the word, ignoring case.
+    Word,
+    /// Tweets whose text matches the regular expression.
+    Regex,
+    /// Tweets and retweets by the user.
+    User,
+    /// Tweets posted from the client, such as "Twitter for iPhone".
+    Source,
+    /// Retweets by the user; their own tweets still show.
+    Retweets,
+}
+
+impl MuteKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Word => "word",
+            Self::Regex => "regex",
+            Self::User => "user",
+            Self::Source => "source",
+            Self::Retweets => "retweets",
+        }
+    }
+}
+
+impl FromStr for MuteKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "word" => Ok(Self::Word),
+            "regex" => Ok(Self::Regex),
+            "user" => Ok(Self::User),
+            "source" => Ok(Self::Source),
+            "retweets" => Ok(Self::Retweets),
+            _ => Err(String::from("use word, regex, user, source or retweets")),
+        }
+    }
+}
+
+impl fmt::Display for MuteKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl MuteRule {
+    pub fn new(kind: MuteKind, value: &str, expires_at: Option<DateTime<Utc>>) -> Result<Self> {
+        let value = match kind {
+            MuteKind::User | MuteKind::Retweets => value.trim_start_matches('@').to_string(),
+            _ => value.to_string(),
+        };
+        if value.is_empty() {
+            bail!("The {} to mute is empty", kind);
+        }
+        if kind == MuteKind::Regex {
+            Regex::new(&value).with_context(|| format!("Invalid regex: {}", value))?;
+        }
+
+        Ok(Self {
+            kind,
+            value,
+            expires_at,
+        })
+    }
+
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.map(|x| x <= now).unwrap_or(false)
+    }
+}
+
+impl Mutes {
+    pub async fn load() -> Result<Self> {
+        let path = Context::mutes_file_path()?;
+        match tokio::fs::read_to_string(&path).await {
+            Ok(text) => toml::from_str(&text)
+                .with_context(|| format!("Invalid mute file: {}", path.display())),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Writes the rules, dropping the ones that have expired.
+    pub async fn save(&mut self) -> Result<()> {
+        let now = Utc::now();
+        self.rules.retain(|x| !x.is_expired(now));
+        let text = toml::to_string_pretty(self)?;
+        write_private(&Context::mutes_file_path()?, text.as_bytes()).await
+    }
+
+    /// Compiles the rules that have not expired by `now`. A regex that does not
+    /// compile, as after editing the file by hand, is skipped with a warning.
+    pub fn filter(&self, now: DateTime<Utc>) -> MuteFilter {
+        let mut filter = MuteFilter::default();
+        for rule in self.rules.iter().filter(|x| !x.is_expired(now)) {
+            let value = rule.value.to_lowercase();
+            match rule.kind {
+                MuteKind::Word => filter.words.push(value),
+                MuteKind::Regex => match Regex::new(&rule.value) {
+                    Ok(regex) => filter.regexes.push(regex),
+                    Err(err) => eprintln!("Skipped the mute regex {}: {}", rule.value, err),
+                },
+                MuteKind::User => {
+                    filter.users.insert(value);
+                }
+                MuteKind::Source => {
+                    filter.sources.insert(value);
+                }
+                MuteKind::Retweets => {
+                    filter.retweets.insert(value);
+                }
+            }
+        }
+        filter
+    }
+}
+
+/// Decides which tweets are hidden. Names are compared in lowercase.
+#[derive(Debug, Clone, Default)]
+pub struct MuteFilter {
+    words: Vec<String>,
+    regexes: Vec<Regex>,
+    users: HashSet<String>,
+    sources: HashSet<String>,
+    retweets: HashSet<String>,
+}
+
+impl MuteFilter {
+    pub fn is_muted(&self, tweet: &TrimTweet) -> bool {
+        let view = TweetView::from(tweet);
+        let text = view.tweet.to_lowercase();
+        let author = view.screen_name.to_lowercase();
+        let retweeter = view.retweet_user_name.as_ref().map(|x| x.to_lowercase());
+        let mut sources = std::iter::once(tweet)
+            .chain(tweet.retweeted_status.as_deref())
+            .filter_map(|x| x.source.as_deref())
+            .map(|x| source_name(x).to_lowercase());
+
+        self.words.iter().any(|x| text.contains(x.as_str()))
+            || self.regexes.iter().any(|x| x.is_match(&view.tweet))
+            || self.users.contains(&author)
+            || retweeter
+                .map(|x| self.users.contains(&x) || self.retweets.contains(&x))
+                .unwrap_or(false)
+            || sources.any(|x| self.sources.contains(&x))
+    }
+
+    /// Drops the muted tweets from the list.
+    pub fn apply(&self, tweet_list: &mut Vec<TrimTweet>) {
+        tweet_list.retain(|x| !self.is_muted(x));
+    }
+}
+
+/// The client name out of a source such as `<a href="...">Twitter for iPhone</a>`.
+fn source_name(source: &str) -> &str {
+    match (source.find('>'), source.rfind("</")) {
+        (Some(start), Some(end)) if start < end => &source[start + 1..end],
+        _ => source,
+    }
+}
@@ -0,0 +1,143 @@
+use anyhow::{Error as AnyError, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::api::{self, Client, RateLimited, ServerError};
+use crate::context::Context;
+use crate::utils::file::{self, write_private};
+
+/// Tweets written while offline, waiting to be posted.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Outbox {
+    pub tweets: Vec<QueuedTweet>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct QueuedTweet {
+    pub account: String,
+    pub status: String,
+    pub queued_at: DateTime<Utc>,
+}
+
+impl Outbox {
+    pub async fn load() -> Result<Self> {
+        match tokio::fs::read_to_string(Context::outbox_path()?).await {
+            Ok(json) => Ok(serde_json::from_str(&json)?),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    pub async fn save(&self) -> Result<()> {
+        let data = serde_json::to_string(self)?;
+        write_private(&Context::outbox_path()?, data.as_bytes()).await
+    }
+
+    /// Holds the outbox lock, so that concurrent runs neither post a tweet twice nor lose one.
+    pub async fn lock() -> Result<std::fs::File> {
+        file::lock(&Context::outbox_path()?.with_extension("lock")).await
+    }
+
+    pub async fn clear() -> Result<()> {
+        let _lock = Self::lock().await?;
+        Self::default().save().await
+    }
+
+    pub async fn push(account: &str, status: &str) -> Result<()> {
+        let _lock = Self::lock().await?;
+        let mut outbox = Self::load().await?;
+        outbox.tweets.push(QueuedTweet {
+            account: account.to_string(),
+            status: status.to_string(),
+            queued_at: Utc::now(),
+        });
+        outbox.save().await
+    }
+
+    /// Posts the queued tweets of `account` in order and returns how many were sent.
+    ///
+    /// When the API cannot take tweets for now (no network, rate limit, rejected
+    /// token or a server error), it stops and the rest stay queued. Only tweets the
+    /// API refuses for their content are removed, and they are listed in the error.
+    pub async fn send(client: &Client, account: &str) -> Result<usize> {
+        let _lock = Self::lock().await?;
+        let mut outbox = Self::load().await?;
+        let queued_count = outbox.tweets.len();
+        let mut remaining = Vec::new();
+        let mut dropped = Vec::new();
+        let mut sent = 0;
+        let mut stopped = None;
+
+        for queued in outbox.tweets.drain(..) {
+            if queued.account != account || stopped.is_some() {
+                remaining.push(queued);
+                continue;
+            }
+            match client.tweet(&queued.status).await {
+                Ok(_) => sent += 1,
+                Err(err) if is_temporary(&err) => {
+                    stopped = Some(err);
+                    remaining.push(queued);
+                }
+                Err(err) => dropped.push(format!("{:#}\n{}", err, queued.status)),
+            }
+        }
+
+        outbox.tweets = remaining;
+        if outbox.tweets.len() != queued_count {
+            outbox.save().await?;
+        }
+
+        if dropped.is_empty() {
+            return match stopped {
+                Some(err) => Err(err),
+                None => Ok(sent),
+            };
+        }
+        Err(Rejected {
+            sent,
+            dropped,
+            stopped: stopped.map(|x| format!("{:#}", x)),
+        }
+        .into())
+    }
+}
+
+/// Queued tweets the API refused for their content, which were removed from the outbox.
+#[derive(Debug)]
+pub struct Rejected {
+    /// How many queued tweets were sent.
+    pub sent: usize,
+    pub dropped: Vec<String>,
+    /// Why the rest of the outbox was not sent, if sending stopped early.
+    pub stopped: Option<String>,
+}
+
+impl fmt::Display for Rejected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} queued tweet(s) were rejected and removed from the outbox:\n\n{}",
+            self.dropped.len(),
+            self.dropped.join("\n\n")
+        )?;
+        if let Some(stopped) = &self.stopped {
+            write!(f, "\n\nThe rest are still queued: {}", stopped)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Rejected {}
+
+/// Whether the failure says nothing about the tweet itself, so it can be sent later.
+pub fn is_temporary(err: &AnyError) -> bool {
+    api::is_connection_error(err)
+        || err.is::<RateLimited>()
+        || err.is::<ServerError>()
+        || err
+            .downcast_ref::<kuon::Error>()
+            .map(api::is_token_rejected)
+            .unwrap_or(false)
+}
@@ -0,0 +1,126 @@
+use kuon::TrimTweet;
+use serde::Serialize;
+
+use crate::component::tweet::TweetView;
+use crate::template;
+
+/// Bumped whenever a field is removed or changes meaning. Added fields keep the version.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// The normalized shape of a tweet written by `--display ndjson`, one per line.
+/// Unlike `--display json`, which mirrors the API, this is documented in the
+/// README and only changes together with `SCHEMA_VERSION`.
+#[derive(Debug, Serialize)]
+pub struct TweetRecord {
+    pub schema: u32,
+    pub id: String,
+    pub created_at: Option<String>,
+    pub url: String,
+    pub text: String,
+    pub lang: Option<String>,
+    pub user: UserRecord,
+    pub retweeted_by: Option<UserRecord>,
+    pub in_reply_to: Option<ReplyRecord>,
+    pub retweet_count: u64,
+    pub favorite_count: u64,
+    pub hashtags: Vec<String>,
+    pub mentions: Vec<String>,
+    pub urls: Vec<String>,
+    pub media: Vec<MediaRecord>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UserRecord {
+    pub id: String,
+    pub name: String,
+    pub screen_name: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReplyRecord {
+    pub id: String,
+    pub screen_name: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MediaRecord {
+    pub id: String,
+    pub url: String,
+    pub expanded_url: String,
+}
+
+impl From<&TrimTweet> for TweetRecord {
+    fn from(tweet: &TrimTweet) -> Self {
+        let view = TweetView::from(tweet);
+        // The retweeted tweet is the one shown, so its entities are the ones that count.
+        let original = tweet.retweeted_status.as_deref().unwrap_or(tweet);
+        let media = original
+            .extended_entities
+            .as_ref()
+            .map(|x| &x.media)
+            .or(original.entities.media.as_ref())
+            .map(|media| {
+                media
+                    .iter()
+                    .map(|x| MediaRecord {
+                        id: x.id_str.clone(),
+                        url: x.media_url_https.clone(),
+                        expanded_url: x.expanded_url.clone(),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Self {
+            schema: SCHEMA_VERSION,
+            url: template::tweet_url(&view),
+            created_at: view.created_at.map(|x| x.to_rfc3339()),
+            text: view.tweet,
+            lang: original.lang.clone(),
+            user: UserRecord {
+                id: original.user.id_str.clone(),
+                name: view.user_name,
+                screen_name: view.screen_name,
+            },
+            retweeted_by: retweeter(tweet),
+            in_reply_to: original
+                .in_reply_to_status_id_str
+                .clone()
+                .map(|id| ReplyRecord {
+                    id,
+                    screen_name: original.in_reply_to_screen_name.clone(),
+                }),
+            retweet_count: view.retweet_count,
+            favorite_count: view.favorite_count,
+            hashtags: original
+                .entities
+                .hashtags
+                .iter()
+                .map(|x| x.text.clone())
+                .collect(),
+            mentions: original
+                .entities
+                .user_mentions
+                .iter()
+                .map(|x| x.screen_name.clone())
+                .collect(),
+            urls: original
+                .entities
+                .urls
+                .iter()
+                .filter_map(|x| x.expanded_url.clone().or_else(|| x.url.clone()))
+                .collect(),
+            media,
+            id: view.id,
+        }
+    }
+}
+
+fn retweeter(tweet: &TrimTweet) -> Option<UserRecord> {
+    tweet.retweeted_status.as_ref()?;
+    Some(UserRecord {
+        id: tweet.user.id_str.clone(),
+        name: tweet.user.name.clone().unwrap_or_default(),
+        screen_name: tweet.user.screen_name.clone().unwrap_or_default(),
+    })
+}
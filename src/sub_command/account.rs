@@ -0,0 +1,91 @@
+use anyhow::{bail, Result};
+use clap::Clap;
+use tokio::io::{stdout, AsyncWriteExt, BufWriter};
+
+use crate::context::Context;
+use crate::utils::crypto::passphrase;
+
+#[derive(Debug, Clap)]
+pub struct Account {
+    #[clap(subcommand)]
+    subcmd: AccountCommand,
+}
+
+#[derive(Debug, Clap)]
+enum AccountCommand {
+    List,
+    Switch(Switch),
+    Remove(Remove),
+    Encrypt,
+    Decrypt,
+}
+
+#[derive(Debug, Clap)]
+struct Switch {
+    name: String,
+}
+
+#[derive(Debug, Clap)]
+struct Remove {
+    name: String,
+}
+
+impl Account {
+    pub async fn run(&self, ctx: Context) -> Result<()> {
+        let mut stdout = BufWriter::new(stdout());
+        let mut accounts = ctx.accounts;
+
+        match &self.subcmd {
+            AccountCommand::List => {
+                for name in accounts.profiles.keys() {
+                    let mark = if accounts.active.as_ref() == Some(name) {
+                        "*"
+                    } else {
+                        " "
+                    };
+                    stdout
+                        .write_all(format!("{} {}\n", mark, name).as_bytes())
+                        .await?;
+                }
+            }
+            AccountCommand::Switch(Switch { name }) => {
+                accounts.switch(name)?;
+                accounts.save().await?;
+                stdout
+                    .write_all(format!("Switched to {}\n", name).as_bytes())
+                    .await?;
+            }
+            AccountCommand::Remove(Remove { name }) => {
+                let active = accounts.remove(name)?.map(str::to_string);
+                accounts.save().await?;
+                stdout
+                    .write_all(format!("Removed {}\n", name).as_bytes())
+                    .await?;
+                if let Some(active) = active {
+                    stdout
+                        .write_all(format!("Switched to {}\n", active).as_bytes())
+                        .await?;
+                }
+            }
+            AccountCommand::Encrypt => {
+                let new = passphrase("New passphrase: ")?;
+                if std::env::var("PETIT_PASSPHRASE").is_err()
+                    && passphrase("Confirm passphrase: ")? != new
+                {
+                    bail!("Passphrases do not match");
+                }
+                accounts.passphrase = Some(new);
+                accounts.save().await?;
+                stdout.write_all(b"Encrypted the account file\n").await?;
+            }
+            AccountCommand::Decrypt => {
+                accounts.passphrase = None;
+                accounts.save().await?;
+                stdout.write_all(b"Decrypted the account file\n").await?;
+            }
+        }
+
+        stdout.flush().await?;
+        Ok(())
+    }
+}
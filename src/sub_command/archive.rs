@@ -0,0 +1,45 @@
+use anyhow::Result;
+use clap::Clap;
+use tokio::io::{stdout, BufWriter};
+
+use crate::archive::Archive as TweetArchive;
+use crate::context::Context;
+use crate::display::{output, OutputOpts};
+
+#[derive(Debug, Clap)]
+pub struct Archive {
+    #[clap(subcommand)]
+    subcmd: ArchiveCommand,
+}
+
+#[derive(Debug, Clap)]
+enum ArchiveCommand {
+    Search(Search),
+}
+
+#[derive(Debug, Clap)]
+struct Search {
+    query: String,
+    #[clap(flatten)]
+    output: OutputOpts,
+    #[clap(long, default_value = "100")]
+    limit: usize,
+}
+
+impl Archive {
+    pub async fn run(&self, mut ctx: Context) -> Result<()> {
+        ctx.load_mutes().await?;
+        let mut stdout = BufWriter::new(stdout());
+
+        match &self.subcmd {
+            ArchiveCommand::Search(search) => {
+                let mut tweet_list = TweetArchive::open()?.search(&search.query, search.limit)?;
+                let format = search.output.format(&ctx.config)?;
+                ctx.mutes.apply(&mut tweet_list);
+                output(&mut stdout, &tweet_list, &format).await?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,141 @@
+use anyhow::{Context as _, Result};
+use chrono::Utc;
+use clap::Clap;
+use tokio::io::{stdout, AsyncWriteExt, BufWriter};
+
+use crate::cache::{Cache as ResponseCache, CacheEntry};
+use crate::context::Context;
+use crate::utils::time;
+
+#[derive(Debug, Clap)]
+pub struct Cache {
+    #[clap(subcommand)]
+    subcmd: CacheCommand,
+}
+
+#[derive(Debug, Clap)]
+enum CacheCommand {
+    Stats(Stats),
+    Clear(Clear),
+    Path,
+}
+
+#[derive(Debug, Clap)]
+struct Stats {
+    /// Show the cache of every account instead of the current one.
+    #[clap(long)]
+    all: bool,
+}
+
+#[derive(Debug, Clap)]
+struct Clear {
+    /// Only remove this entry, such as `home` or `search?q=rust`.
+    key: Option<String>,
+    /// Remove the cache of every account instead of the current one.
+    #[clap(long, conflicts_with = "key")]
+    all: bool,
+}
+
+impl Cache {
+    pub async fn run(&self, ctx: Context) -> Result<()> {
+        let mut stdout = BufWriter::new(stdout());
+
+        match &self.subcmd {
+            CacheCommand::Stats(Stats { all: true }) => {
+                let text = ResponseCache::load_all()
+                    .await?
+                    .iter()
+                    .map(stats)
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                stdout.write_all(text.as_bytes()).await?;
+            }
+            CacheCommand::Stats(Stats { all: false }) => {
+                let account = ctx.account;
+                let cache = ctx.cache.unwrap_or_else(|| ResponseCache::new(account));
+                let text = stats(&cache);
+                stdout.write_all(text.as_bytes()).await?;
+            }
+            CacheCommand::Clear(Clear { all: true, .. }) => {
+                ResponseCache::remove_all().await?;
+                stdout
+                    .write_all(b"Cleared the cache of every account\n")
+                    .await?;
+            }
+            CacheCommand::Clear(Clear { key: None, .. }) => {
+                let account = ctx.account.as_deref().with_context(|| {
+                    "Not logged in. pass --all to clear the cache of every account"
+                })?;
+                ResponseCache::remove(account).await?;
+                stdout
+                    .write_all(format!("Cleared the cache of {}\n", account).as_bytes())
+                    .await?;
+            }
+            CacheCommand::Clear(Clear { key: Some(key), .. }) => {
+                let mut removed = None;
+                ResponseCache::update(ctx.account, |cache| removed = cache.entries.remove(key))
+                    .await?;
+                removed.with_context(|| format!("No such cache entry: {}", key))?;
+                stdout
+                    .write_all(format!("Removed {} from the cache\n", key).as_bytes())
+                    .await?;
+            }
+            CacheCommand::Path => {
+                let path = Context::cache_file_path()?;
+                stdout
+                    .write_all(format!("{}\n", path.display()).as_bytes())
+                    .await?;
+            }
+        }
+
+        stdout.flush().await?;
+        Ok(())
+    }
+}
+
+fn stats(cache: &ResponseCache) -> String {
+    let mut text = format!(
+        "account: {}\n{:<32} {:>6} {:>6} {:>6} {:>6} {:>6}\n",
+        cache.account.as_deref().unwrap_or("-"),
+        "ENTRY",
+        "TWEETS",
+        "AGE",
+        "HITS",
+        "MISSES",
+        "RATIO"
+    );
+    for (key, entry) in cache.entries.iter() {
+        text += &row(
+            key,
+            entry.timeline.len(),
+            entry
+                .latest_call
+                .map(|x| time::relative(Utc::now() - x))
+                .unwrap_or_else(|| String::from("-")),
+            entry.hits,
+            entry.misses,
+        );
+    }
+    let total = cache
+        .entries
+        .values()
+        .fold(CacheEntry::default(), |mut total, x| {
+            total.hits += x.hits;
+            total.misses += x.misses;
+            total
+        });
+    let tweets = cache.entries.values().map(|x| x.timeline.len()).sum();
+    text += &row("total", tweets, String::from("-"), total.hits, total.misses);
+    text
+}
+
+fn row(key: &str, tweets: usize, age: String, hits: u64, misses: u64) -> String {
+    let ratio = match hits + misses {
+        0 => String::from("-"),
+        calls => format!("{}%", hits * 100 / calls),
+    };
+    format!(
+        "{:<32} {:>6} {:>6} {:>6} {:>6} {:>6}\n",
+        key, tweets, age, hits, misses, ratio
+    )
+}
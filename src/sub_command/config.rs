@@ -0,0 +1,137 @@
+use anyhow::{bail, Context as _, Result};
+use clap::Clap;
+use serde::Deserialize;
+use tokio::io::{stdout, AsyncWriteExt, BufWriter};
+use toml::Value;
+
+use crate::config::Config as PetitConfig;
+use crate::context::Context;
+
+#[derive(Debug, Clap)]
+pub struct Config {
+    #[clap(subcommand)]
+    subcmd: ConfigCommand,
+}
+
+#[derive(Debug, Clap)]
+enum ConfigCommand {
+    Get(Get),
+    Set(Set),
+    Path,
+}
+
+#[derive(Debug, Clap)]
+struct Get {
+    key: String,
+}
+
+#[derive(Debug, Clap)]
+struct Set {
+    key: String,
+    value: String,
+}
+
+impl Config {
+    pub async fn run(&self, ctx: Context) -> Result<()> {
+        let mut stdout = BufWriter::new(stdout());
+
+        match &self.subcmd {
+            ConfigCommand::Get(Get { key }) => {
+                let config = Value::try_from(&ctx.config)?;
+                let value = lookup(&config, key).with_context(|| format!("{} is not set", key))?;
+                let text = match value {
+                    Value::String(s) => s.clone(),
+                    Value::Table(_) => toml::to_string_pretty(value)?,
+                    other => other.to_string(),
+                };
+                stdout.write_all(text.as_bytes()).await?;
+                stdout.write_all(b"\n").await?;
+            }
+            ConfigCommand::Set(Set { key, value }) => {
+                let config = Value::try_from(&ctx.config)?;
+                let candidates = match lookup(&config, key) {
+                    Some(Value::Integer(_)) => vec![Value::Integer(
+                        value
+                            .parse()
+                            .with_context(|| format!("{} must be an integer", key))?,
+                    )],
+                    Some(Value::Boolean(_)) => vec![Value::Boolean(
+                        value
+                            .parse()
+                            .with_context(|| format!("{} must be true or false", key))?,
+                    )],
+                    Some(Value::Table(_)) => bail!("{} is a section", key),
+                    Some(_) => vec![Value::String(value.clone())],
+                    // Keys that are not set yet, such as those of cache.endpoint_ttl, have
+                    // no type to go by. The value is kept as text unless the config
+                    // only takes it as a number or a boolean.
+                    None => std::iter::once(Value::String(value.clone()))
+                        .chain(value.parse().map(Value::Integer))
+                        .chain(value.parse().map(Value::Boolean))
+                        .collect(),
+                };
+
+                let table = PetitConfig::load_table().await?;
+                let mut first_err = None;
+                let mut saved = None;
+                for candidate in candidates {
+                    let mut updated = table.clone();
+                    insert(&mut updated, key, candidate)?;
+                    match PetitConfig::deserialize(updated.clone()) {
+                        Ok(config) => {
+                            saved = Some((updated, config));
+                            break;
+                        }
+                        Err(err) => {
+                            first_err.get_or_insert(err);
+                        }
+                    }
+                }
+                let (table, config) = match (saved, first_err) {
+                    (Some(saved), _) => saved,
+                    (None, Some(err)) => return Err(err.into()),
+                    (None, None) => unreachable!("there is always a candidate"),
+                };
+
+                if lookup(&Value::try_from(config)?, key).is_none() {
+                    bail!("Unknown config key: {}", key);
+                }
+                PetitConfig::save_table(&table).await?;
+            }
+            ConfigCommand::Path => {
+                let path = Context::config_file_path()?;
+                stdout
+                    .write_all(format!("{}\n", path.display()).as_bytes())
+                    .await?;
+            }
+        }
+
+        stdout.flush().await?;
+        Ok(())
+    }
+}
+
+fn lookup<'a>(value: &'a Value, key: &str) -> Option<&'a Value> {
+    key.split('.')
+        .try_fold(value, |value, name| value.get(name))
+}
+
+fn insert(table: &mut Value, key: &str, value: Value) -> Result<()> {
+    let mut names = key.split('.').collect::<Vec<_>>();
+    let last = names.pop().with_context(|| "Empty config key")?;
+
+    let mut current = table;
+    for name in names {
+        current = current
+            .as_table_mut()
+            .with_context(|| format!("{} is not a section", name))?
+            .entry(name)
+            .or_insert_with(|| Value::Table(Default::default()));
+    }
+    current
+        .as_table_mut()
+        .with_context(|| format!("Cannot set {}", key))?
+        .insert(last.to_string(), value);
+
+    Ok(())
+}
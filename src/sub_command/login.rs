@@ -1,64 +1,183 @@
-use crate::{context::Context, utils::stdio::read_stdin};
-use anyhow::Result;
+use crate::{account::Accounts, api, context::Context, utils::stdio::read_stdin};
+use anyhow::{bail, Result};
 use clap::Clap;
-use kuon::{Callback, OAuthRequestToken, OAuthToken, TwitterAPI};
+use kuon::OAuthToken;
+use std::collections::HashMap;
 use tokio::{
-    fs::File,
-    io::{stdout, AsyncWriteExt, BufWriter},
+    io::{stdout, AsyncReadExt, AsyncWriteExt, BufWriter, Stdout},
+    net::TcpListener,
 };
 
 #[derive(Debug, Clap)]
-pub struct Login {}
+pub struct Login {
+    #[clap(long)]
+    name: Option<String>,
+    /// Receive the verifier on http://127.0.0.1:<port>/callback instead of typing a PIN.
+    /// The URL has to be registered as a callback URL of the app.
+    #[clap(long)]
+    callback: bool,
+    #[clap(long, default_value = "8080")]
+    port: u16,
+    /// Import an existing access token instead of logging in interactively.
+    #[clap(
+        long,
+        env = "PETIT_ACCESS_TOKEN",
+        requires = "secret",
+        conflicts_with = "callback"
+    )]
+    token: Option<String>,
+    #[clap(
+        long,
+        env = "PETIT_ACCESS_TOKEN_SECRET",
+        hide_env_values = true,
+        requires = "token"
+    )]
+    secret: Option<String>,
+}
 
 impl Login {
     pub async fn run(&self, ctx: Context) -> Result<()> {
         let mut stdout = BufWriter::new(stdout());
 
-        if ctx.client.is_some() {
-            stdout.write_all(b"Already logged in!!").await?;
-            stdout.flush().await?;
-            return Ok(());
-        }
+        let oauth_token = match (&self.token, &self.secret) {
+            (Some(token), Some(secret)) => OAuthToken {
+                token: token.clone(),
+                secret: secret.clone(),
+            },
+            _ => self.authorize(&ctx, &mut stdout).await?,
+        };
 
-        let builder = TwitterAPI::builder()
-            .api_key(ctx.api_key.clone())
-            .api_secret_key(ctx.api_secret.clone());
-        let request_token = builder.pre_build(Callback::Pin).await?;
+        let client = ctx.build_client(oauth_token)?;
+        let user = client.verify_credentials().await?;
+        let name = self
+            .name
+            .clone()
+            .or_else(|| ctx.account.clone())
+            .unwrap_or(user.screen_name);
+        let mut accounts = ctx.accounts;
+        Self::write_token(&mut accounts, &name, client.oauth_token().clone()).await?;
 
         stdout
-            .write_all(
-                format!("Please access {} and login\n", oauth_url(&request_token)).as_bytes(),
-            )
+            .write_all(format!("Logged in successfully as {}!", name).as_bytes())
             .await?;
-        stdout.write_all(b"Input pin code : ").await?;
         stdout.flush().await?;
 
-        let pin = read_stdin()?;
-        let api = builder.build(request_token, &pin).await?;
+        Ok(())
+    }
 
-        let oauth_token = api.oauth_token();
-        Self::write_token(&oauth_token).await?;
+    /// Runs the PIN or callback flow against the configured base URL.
+    async fn authorize(&self, ctx: &Context, stdout: &mut BufWriter<Stdout>) -> Result<OAuthToken> {
+        let credentials = ctx.credentials()?;
+        let listener = if self.callback {
+            match TcpListener::bind(("127.0.0.1", self.port)).await {
+                Ok(listener) => Some(listener),
+                Err(err) => {
+                    eprintln!(
+                        "Could not listen on port {} ({}), falling back to PIN login",
+                        self.port, err
+                    );
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        let callback = match listener {
+            Some(_) => format!("http://127.0.0.1:{}/callback", self.port),
+            None => String::from("oob"),
+        };
+        let request_token = api::request_token(credentials, &ctx.base_url, &callback).await?;
 
-        stdout.write_all(b"Logged in successfully!").await?;
-        stdout.flush().await?;
+        stdout
+            .write_all(
+                format!(
+                    "Please access {} and login\n",
+                    api::authorize_url(&ctx.base_url, &request_token)
+                )
+                .as_bytes(),
+            )
+            .await?;
 
-        Ok(())
+        let verifier = match listener {
+            Some(listener) => {
+                stdout.write_all(b"Waiting for the callback...\n").await?;
+                stdout.flush().await?;
+                wait_for_verifier(listener, &request_token.token).await?
+            }
+            None => {
+                stdout.write_all(b"Input pin code : ").await?;
+                stdout.flush().await?;
+                read_stdin()?
+            }
+        };
+        api::access_token(credentials, &ctx.base_url, &request_token, &verifier).await
     }
 
-    async fn write_token(oauth_token: &OAuthToken) -> Result<()> {
-        let serialized = serde_json::to_string(oauth_token)?;
-        let file = File::create(Context::oauth_token_path()).await?;
-        let mut writer = BufWriter::new(file);
-        writer.write_all(serialized.as_bytes()).await?;
-        writer.flush().await?;
+    async fn write_token(
+        accounts: &mut Accounts,
+        name: &str,
+        oauth_token: OAuthToken,
+    ) -> Result<()> {
+        accounts.insert(name, oauth_token);
+        accounts.save().await
+    }
+}
 
-        Ok(())
+/// Serves requests until `/callback` is called back for `request_token`, then drops
+/// the listener. Requests for other tokens are refused, so that another page cannot
+/// slip in a verifier of its own.
+async fn wait_for_verifier(listener: TcpListener, request_token: &str) -> Result<String> {
+    loop {
+        let (mut stream, _) = listener.accept().await?;
+
+        let mut buf = vec![0; 4096];
+        let len = stream.read(&mut buf).await?;
+        let request = String::from_utf8_lossy(&buf[..len]);
+        let target = request
+            .lines()
+            .next()
+            .and_then(|x| x.split_whitespace().nth(1))
+            .unwrap_or_default();
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        if path != "/callback" {
+            respond(&mut stream, "404 Not Found", "Not Found").await?;
+            continue;
+        }
+        let params = url::form_urlencoded::parse(query.as_bytes())
+            .into_owned()
+            .collect::<HashMap<_, _>>();
+        if params.get("oauth_token").map(String::as_str) != Some(request_token)
+            && params.get("denied").map(String::as_str) != Some(request_token)
+        {
+            respond(&mut stream, "400 Bad Request", "Unknown login request.").await?;
+            continue;
+        }
+
+        if let Some(verifier) = params.get("oauth_verifier") {
+            respond(
+                &mut stream,
+                "200 OK",
+                "Logged in to petit. You can close this window.",
+            )
+            .await?;
+            return Ok(verifier.to_string());
+        }
+        if params.contains_key("denied") {
+            respond(&mut stream, "200 OK", "Login was cancelled.").await?;
+            bail!("Authorization was denied");
+        }
+        respond(&mut stream, "404 Not Found", "Not Found").await?;
     }
 }
 
-fn oauth_url(request_token: &OAuthRequestToken) -> String {
-    format!(
-        "https://api.twitter.com/oauth/authorize?oauth_token={}",
-        request_token.token
-    )
+async fn respond(stream: &mut tokio::net::TcpStream, status: &str, body: &str) -> Result<()> {
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.flush().await?;
+    Ok(())
 }
@@ -0,0 +1,51 @@
+use anyhow::{Context as _, Result};
+use clap::Clap;
+use tokio::io::{stdout, AsyncWriteExt, BufWriter};
+
+use crate::cache::Cache;
+use crate::context::Context;
+
+#[derive(Debug, Clap)]
+pub struct Logout {
+    #[clap(long)]
+    keep_cache: bool,
+}
+
+impl Logout {
+    pub async fn run(&self, ctx: Context) -> Result<()> {
+        let mut stdout = BufWriter::new(stdout());
+
+        let name = ctx.account.clone().with_context(|| "Not logged in")?;
+
+        // The local token is removed even if the API refuses to revoke it,
+        // otherwise an already revoked token could never be forgotten.
+        match ctx.client() {
+            Ok(client) => {
+                if let Err(err) = client.invalidate_token().await {
+                    eprintln!("Failed to revoke the access token: {}", err);
+                }
+            }
+            Err(err) => eprintln!("Failed to revoke the access token: {}", err),
+        }
+
+        let mut accounts = ctx.accounts;
+        let active = accounts.remove(&name)?.map(str::to_string);
+        accounts.save().await?;
+
+        if !self.keep_cache && ctx.cache.is_some() {
+            Cache::remove(&name).await?;
+        }
+
+        stdout
+            .write_all(format!("Logged out from {}\n", name).as_bytes())
+            .await?;
+        if let Some(active) = active {
+            stdout
+                .write_all(format!("Switched to {}\n", active).as_bytes())
+                .await?;
+        }
+        stdout.flush().await?;
+
+        Ok(())
+    }
+}
@@ -1,31 +1,158 @@
 use anyhow::Result;
+use chrono::{DateTime, Utc};
 use clap::Clap;
+use kuon::TrimTweet;
+use std::future::Future;
 
+use crate::api::{snowflake_id, Page, RateLimited};
+use crate::archive::Archive;
 use crate::context::Context;
+use crate::display::TweetWriter;
+use crate::utils::color;
+use crate::utils::time::parse_time_or_ago;
 
+mod account;
+mod archive;
+mod cache;
+mod config;
 mod login;
+mod logout;
+mod mute;
+mod outbox;
 mod search;
 mod timeline;
 mod tweet;
+mod whoami;
 
 #[derive(Debug, Clap)]
 pub enum SubCommand {
     Login(login::Login),
+    Logout(logout::Logout),
     Tweet(tweet::Tweet),
     #[clap(name = "tl")]
     TimeLine(timeline::TimeLine),
     Search(search::Search),
+    Account(account::Account),
+    Config(config::Config),
+    #[clap(name = "whoami")]
+    WhoAmI(whoami::WhoAmI),
+    Archive(archive::Archive),
+    Cache(cache::Cache),
+    Outbox(outbox::Outbox),
+    Mute(mute::Mute),
 }
 
 impl SubCommand {
     pub async fn run(&self, ctx: Context) -> Result<()> {
         match self {
             SubCommand::Login(login) => login.run(ctx).await?,
+            SubCommand::Logout(logout) => logout.run(ctx).await?,
             SubCommand::Tweet(tweet) => tweet.run(ctx).await?,
             SubCommand::TimeLine(tl) => tl.run(ctx).await?,
             SubCommand::Search(search) => search.run(ctx).await?,
+            SubCommand::Account(account) => account.run(ctx).await?,
+            SubCommand::Config(config) => config.run(ctx).await?,
+            SubCommand::WhoAmI(whoami) => whoami.run(ctx).await?,
+            SubCommand::Archive(archive) => archive.run(ctx).await?,
+            SubCommand::Cache(cache) => cache.run(ctx).await?,
+            SubCommand::Outbox(outbox) => outbox.run(ctx).await?,
+            SubCommand::Mute(mute) => mute.run(ctx).await?,
         }
 
         Ok(())
     }
 }
+
+/// A time range of tweets, shared by the commands that list them.
+#[derive(Debug, Clap)]
+pub(crate) struct RangeOpts {
+    /// Only tweets posted at or after this time, such as 2021-03-06, "2021-03-06 12:00" or 1d (ago)
+    #[clap(long, parse(try_from_str = parse_time_or_ago))]
+    since: Option<DateTime<Utc>>,
+    /// Only tweets posted before this time
+    #[clap(long, parse(try_from_str = parse_time_or_ago))]
+    until: Option<DateTime<Utc>>,
+}
+
+impl RangeOpts {
+    pub fn is_set(&self) -> bool {
+        self.since.is_some() || self.until.is_some()
+    }
+
+    pub fn since(&self) -> Option<DateTime<Utc>> {
+        self.since
+    }
+
+    /// The `since_id` that leaves out tweets older than `--since`, or `since_id` when it is newer.
+    pub fn since_id(&self, since_id: Option<u64>) -> Option<u64> {
+        let from_time = self.since.map(|x| snowflake_id(x).saturating_sub(1));
+        since_id.max(from_time)
+    }
+
+    /// The `max_id` that leaves out tweets from `--until` on, or `max_id` when it is older.
+    pub fn max_id(&self, max_id: Option<u64>) -> Option<u64> {
+        let from_time = self.until.map(|x| snowflake_id(x).saturating_sub(1));
+        match (max_id, from_time) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
+}
+
+/// Most pages fetched by a walk without a page count, in case the API keeps
+/// answering with tweets.
+const MAX_PAGES: u32 = 1000;
+
+/// Pages backwards from `page` with `fetch`, archiving every page and writing it as
+/// soon as it arrives, until a page comes back empty or `pages` pages are fetched.
+/// When the rate limit is hit midway, the output written so far is closed properly
+/// before the error is returned.
+pub(crate) async fn walk_pages<F, Fut>(
+    ctx: &Context,
+    mut writer: TweetWriter<'_>,
+    mut page: Page,
+    pages: Option<u32>,
+    mut fetch: F,
+) -> Result<()>
+where
+    F: FnMut(Page) -> Fut,
+    Fut: Future<Output = Result<Vec<TrimTweet>>>,
+{
+    let mut fetched = 0;
+    while fetched < pages.unwrap_or(MAX_PAGES) {
+        let mut tweets = match fetch(page.clone()).await {
+            Ok(tweets) => tweets,
+            Err(err) if err.downcast_ref::<RateLimited>().is_some() => {
+                writer.finish().await?;
+                if fetched == 0 {
+                    return Err(err);
+                }
+                return Err(err.context(format!(
+                    "Stopped after {} page(s); the tweets written so far are complete",
+                    fetched
+                )));
+            }
+            Err(err) => return Err(err),
+        };
+        let oldest = match tweets.last() {
+            Some(tweet) => tweet.id,
+            None => break,
+        };
+        Archive::keep(&tweets);
+        ctx.mutes.apply(&mut tweets);
+        writer.write(&tweets).await?;
+        fetched += 1;
+        page.max_id = match oldest.checked_sub(1) {
+            Some(id) => Some(id),
+            None => break,
+        };
+    }
+    if pages.is_none() && fetched == MAX_PAGES {
+        eprintln!(
+            "{}",
+            color::warning(format!("Stopped after {} pages", MAX_PAGES))
+        );
+    }
+
+    writer.finish().await
+}
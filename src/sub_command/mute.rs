@@ -0,0 +1,109 @@
+use anyhow::{bail, Context as _, Result};
+use chrono::{Local, Utc};
+use clap::Clap;
+use std::time::Duration;
+use tokio::io::{stdout, AsyncWriteExt, BufWriter};
+
+use crate::context::Context;
+use crate::mute::{MuteKind, MuteRule, Mutes};
+use crate::utils::time::{parse_datetime, parse_duration};
+
+#[derive(Debug, Clap)]
+pub struct Mute {
+    #[clap(subcommand)]
+    subcmd: MuteCommand,
+}
+
+#[derive(Debug, Clap)]
+enum MuteCommand {
+    Add(Add),
+    List,
+    Remove(Remove),
+}
+
+#[derive(Debug, Clap)]
+struct Add {
+    /// word, regex, user, source or retweets
+    kind: MuteKind,
+    value: String,
+    /// Expire after this long, such as 12h or 7d
+    #[clap(long = "for", parse(try_from_str = parse_duration), conflicts_with = "until")]
+    duration: Option<Duration>,
+    /// Expire at this time, such as 2021-03-06 or 2021-03-06 12:00
+    #[clap(long)]
+    until: Option<String>,
+}
+
+#[derive(Debug, Clap)]
+struct Remove {
+    /// Number shown by "petit mute list"
+    number: usize,
+}
+
+impl Mute {
+    pub async fn run(&self, _ctx: Context) -> Result<()> {
+        let mut stdout = BufWriter::new(stdout());
+        let mut mutes = Mutes::load().await?;
+
+        match &self.subcmd {
+            MuteCommand::Add(add) => {
+                let now = Utc::now();
+                let expires_at = match (&add.duration, &add.until) {
+                    (Some(duration), _) => {
+                        if duration.as_secs() == 0 {
+                            bail!("--for must be longer than 0s");
+                        }
+                        let expires_at = chrono::Duration::from_std(*duration)
+                            .ok()
+                            .and_then(|x| now.checked_add_signed(x))
+                            .with_context(|| "--for is too long")?;
+                        Some(expires_at)
+                    }
+                    (None, Some(until)) => {
+                        let until_time = parse_datetime(until)?;
+                        if until_time <= now {
+                            bail!("--until {} is already past", until);
+                        }
+                        Some(until_time)
+                    }
+                    (None, None) => None,
+                };
+                let rule = MuteRule::new(add.kind, &add.value, expires_at)?;
+                let text = format!("Muted {} {}\n", rule.kind, rule.value);
+                if !mutes.rules.contains(&rule) {
+                    mutes.rules.push(rule);
+                    mutes.save().await?;
+                }
+                stdout.write_all(text.as_bytes()).await?;
+            }
+            MuteCommand::List => {
+                let now = Utc::now();
+                for (i, rule) in mutes.rules.iter().enumerate() {
+                    let expires_at = match rule.expires_at {
+                        Some(_) if rule.is_expired(now) => String::from("expired"),
+                        Some(x) => {
+                            format!("until {}", x.with_timezone(&Local).format("%Y-%m-%d %H:%M"))
+                        }
+                        None => String::new(),
+                    };
+                    let line =
+                        format!("{}\t{}\t{}\t{}\n", i + 1, rule.kind, rule.value, expires_at);
+                    stdout.write_all(line.as_bytes()).await?;
+                }
+            }
+            MuteCommand::Remove(remove) => {
+                if remove.number == 0 || remove.number > mutes.rules.len() {
+                    bail!("No such mute: {}. run \"petit mute list\"", remove.number);
+                }
+                let rule = mutes.rules.remove(remove.number - 1);
+                mutes.save().await?;
+                stdout
+                    .write_all(format!("Unmuted {} {}\n", rule.kind, rule.value).as_bytes())
+                    .await?;
+            }
+        }
+
+        stdout.flush().await?;
+        Ok(())
+    }
+}
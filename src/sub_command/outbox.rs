@@ -0,0 +1,58 @@
+use anyhow::{Context as _, Result};
+use chrono::Local;
+use clap::Clap;
+use tokio::io::{stdout, AsyncWriteExt, BufWriter};
+
+use crate::context::Context;
+use crate::outbox::Outbox as QueuedTweets;
+
+#[derive(Debug, Clap)]
+pub struct Outbox {
+    #[clap(subcommand)]
+    subcmd: OutboxCommand,
+}
+
+#[derive(Debug, Clap)]
+enum OutboxCommand {
+    List,
+    Send,
+    Clear,
+}
+
+impl Outbox {
+    pub async fn run(&self, ctx: Context) -> Result<()> {
+        let mut stdout = BufWriter::new(stdout());
+
+        match &self.subcmd {
+            OutboxCommand::List => {
+                for queued in QueuedTweets::load().await?.tweets {
+                    let line = format!(
+                        "{}\t{}\t{}\n",
+                        queued.account,
+                        queued
+                            .queued_at
+                            .with_timezone(&Local)
+                            .format("%Y-%m-%d %H:%M:%S"),
+                        queued.status.replace('\n', " ")
+                    );
+                    stdout.write_all(line.as_bytes()).await?;
+                }
+            }
+            OutboxCommand::Send => {
+                let client = ctx.client()?;
+                let account = ctx.account.as_deref().with_context(|| "Not logged in")?;
+                let sent = QueuedTweets::send(client, account).await?;
+                stdout
+                    .write_all(format!("Sent {} queued tweets\n", sent).as_bytes())
+                    .await?;
+            }
+            OutboxCommand::Clear => {
+                QueuedTweets::clear().await?;
+                stdout.write_all(b"Cleared the outbox\n").await?;
+            }
+        }
+
+        stdout.flush().await?;
+        Ok(())
+    }
+}
@@ -1,38 +1,66 @@
-use crate::component::{input::Input, Component, ComponentWithContext};
+use crate::api::{self, Client, Page};
+use crate::archive::Archive;
+use crate::cache::{self, Cache};
+use crate::component::{input::Input, ComponentWithContext};
+use crate::config::Config;
 use crate::context::Context;
+use crate::display::{output, stale_notice, Format, OutputOpts, TweetWriter};
+use crate::sub_command::{walk_pages, RangeOpts};
+use crate::utils::color;
 use crate::utils::event::{Event, Events};
 use crate::utils::terminal::create_terminal;
 use anyhow::{Context as _, Result};
+use chrono::Utc;
 use clap::Clap;
+use kuon::TrimTweet;
+use std::time::Duration;
 use termion::event::Key;
+use tokio::io::{stdout, BufWriter};
 use tui::layout::{Constraint, Layout};
+use tui::style::{Color, Style};
+use tui::text::Span;
 use tui::widgets::{Block, BorderType, Borders, List};
 
 #[derive(Debug, Clap)]
 pub struct Search {
     query: Option<String>,
+    /// With either option, or when stdout is not a terminal, results are printed instead of opening the search view
+    #[clap(flatten)]
+    output: OutputOpts,
+    /// Results in the range are printed, paging back until it is exhausted
+    #[clap(flatten)]
+    range: RangeOpts,
 }
 
+/// The most results the search API returns per request.
+const SEARCH_COUNT: u64 = 100;
+/// How far back the standard search API goes.
+const SEARCH_WINDOW_DAYS: i64 = 7;
+
 impl Search {
-    pub async fn run(&self, ctx: Context) -> Result<()> {
-        let client = ctx
-            .client
-            .with_context(|| "Please login. run \"petit login\"")?;
+    pub async fn run(&self, mut ctx: Context) -> Result<()> {
+        ctx.load_mutes().await?;
+        let client = if ctx.offline {
+            None
+        } else {
+            Some(ctx.client()?)
+        };
+        let config = &ctx.config;
+        if self.output.is_set() || self.range.is_set() || !termion::is_tty(&std::io::stdout()) {
+            return self.print(client, &ctx).await;
+        }
+
         let mut terminal = create_terminal()?;
-        let mut events = Events::new();
+        let mut events = Events::new(Duration::from_millis(config.ui.tick_rate));
         let mut input = Input {
             value: self.query.clone().unwrap_or_default(),
         };
-        let mut tweet_list = if input.value.is_empty() {
-            Vec::new()
+        let (mut tweet_list, mut notice) = if input.value.is_empty() {
+            (Vec::new(), None)
         } else {
-            client
-                .search_tweets()
-                .q(input.value.as_str())
-                .send()
-                .await?
-                .statuses
+            search(client, &ctx.account, config, &input.value).await?
         };
+        ctx.mutes.apply(&mut tweet_list);
 
         loop {
             terminal.draw(|f| {
@@ -51,35 +79,40 @@ impl Search {
                 f.render_widget(input_widget, chunk[0]);
 
                 let result_block = Block::default()
-                    .title("Result")
+                    .title(match &notice {
+                        Some(notice) => Span::styled(
+                            format!("Result ({})", notice),
+                            Style::default().fg(Color::Yellow),
+                        ),
+                        None => Span::raw("Result"),
+                    })
                     .border_type(BorderType::Rounded)
                     .borders(Borders::ALL);
                 let tweet_widget = if tweet_list.is_empty() {
                     List::new(vec![])
                 } else {
-                    tweet_list.view(&chunk[1]).block(result_block)
+                    tweet_list.view(&chunk[1], config).block(result_block)
                 };
                 f.render_widget(tweet_widget, chunk[1]);
             })?;
 
             match events.next().await {
-                Some(Event::Input(Key::Esc)) => {
+                Some(Event::Input(key)) if key == config.keys.quit => {
                     break;
                 }
-                Some(Event::Input(Key::Char('\n'))) => {
-                    tweet_list = client
-                        .search_tweets()
-                        .q(input.value.as_str())
-                        .send()
-                        .await?
-                        .statuses;
+                Some(Event::Input(key)) if key == config.keys.search => {
+                    let (tweets, stale) =
+                        search(client, &ctx.account, config, &input.value).await?;
+                    tweet_list = tweets;
+                    ctx.mutes.apply(&mut tweet_list);
+                    notice = stale;
+                }
+                Some(Event::Input(key)) if key == config.keys.delete => {
+                    input.value.pop();
                 }
                 Some(Event::Input(Key::Char(c))) => {
                     input.value.push(c);
                 }
-                Some(Event::Input(Key::Backspace)) => {
-                    input.value.pop();
-                }
                 // Some(Event::Input(Key::Down)) => offset += 1,
                 // Some(Event::Input(Key::Up)) => {
                 //     offset = if offset == 0 { offset } else { offset - 1 }
@@ -93,4 +126,116 @@ impl Search {
 
         Ok(())
     }
+
+    async fn print(&self, client: Option<&Client>, ctx: &Context) -> Result<()> {
+        let query = self
+            .query
+            .as_deref()
+            .with_context(|| "Pass a query to print the results")?;
+        let format = self.output.format(&ctx.config)?;
+        if self.range.is_set() {
+            return self.walk(client, ctx, query, &format).await;
+        }
+        let (mut tweet_list, notice) = search(client, &ctx.account, &ctx.config, query).await?;
+        if let Some(notice) = notice {
+            eprintln!("{}", color::warning(notice));
+        }
+
+        let mut stdout = BufWriter::new(stdout());
+        ctx.mutes.apply(&mut tweet_list);
+        output(&mut stdout, &tweet_list, &format).await
+    }
+
+    /// Pages back through recent results between `--since` and `--until`, writing every
+    /// page as it arrives. Offline, the archived tweets in the range are printed instead.
+    async fn walk(
+        &self,
+        client: Option<&Client>,
+        ctx: &Context,
+        query: &str,
+        format: &Format,
+    ) -> Result<()> {
+        let since_id = self.range.since_id(None);
+        let max_id = self.range.max_id(None);
+        let mut stdout = BufWriter::new(stdout());
+        let mut writer = TweetWriter::begin(&mut stdout, format).await?;
+
+        let client = match client {
+            Some(client) => client,
+            None => {
+                eprintln!("{}", color::warning(stale_notice(None)));
+                let mut tweet_list = Archive::open()?.search(query, ctx.config.cache.limit)?;
+                tweet_list.retain(|x| {
+                    since_id.map(|id| x.id > id).unwrap_or(true)
+                        && max_id.map(|id| x.id <= id).unwrap_or(true)
+                });
+                ctx.mutes.apply(&mut tweet_list);
+                writer.write(&tweet_list).await?;
+                return writer.finish().await;
+            }
+        };
+        let window = chrono::Duration::days(SEARCH_WINDOW_DAYS);
+        if matches!(self.range.since(), Some(x) if x < Utc::now() - window) {
+            eprintln!(
+                "{}",
+                color::warning(format!(
+                    "The search API only returns tweets of the last {} days; \
+                     older tweets in the range are left out",
+                    SEARCH_WINDOW_DAYS
+                ))
+            );
+        }
+        let page = Page {
+            count: Some(SEARCH_COUNT),
+            since_id,
+            max_id,
+        };
+        walk_pages(ctx, writer, page, None, |page| async move {
+            client.search_recent(query, &page).await
+        })
+        .await
+    }
+}
+
+/// Returns the results and, when they do not come from the API, a notice saying so.
+async fn search(
+    client: Option<&Client>,
+    account: &Option<String>,
+    config: &Config,
+    query: &str,
+) -> Result<(Vec<TrimTweet>, Option<String>)> {
+    let key = Cache::key(cache::SEARCH, &[("q", query)]);
+
+    if let Some(client) = client {
+        let ttl = config.cache.ttl(cache::SEARCH);
+        if let Some(entry) = Cache::hit(account.clone(), &key, ttl).await? {
+            return Ok((entry.timeline, None));
+        }
+
+        match client.search_tweets(query).await {
+            Ok(tweets) => {
+                Archive::keep(&tweets);
+                // Results come in relevance order, so they replace the entry instead of merging.
+                Cache::store(account.clone(), &key, |entry| {
+                    entry.timeline = tweets.clone()
+                })
+                .await?;
+                return Ok((tweets, None));
+            }
+            Err(err) if api::is_connection_error(&err) => {}
+            Err(err) => return Err(err),
+        }
+    }
+
+    // Offline: the last results of the same query, or else everything archived.
+    let cached = Cache::load(account.as_deref())
+        .await?
+        .and_then(|mut x| x.entries.remove(&key));
+    match cached {
+        Some(entry) => Ok((entry.timeline, Some(stale_notice(entry.latest_call)))),
+        None => Ok((
+            Archive::open()?.search(query, config.cache.limit)?,
+            Some(stale_notice(None)),
+        )),
+    }
 }
@@ -1,140 +1,387 @@
-use std::str::FromStr;
-
-use crate::component::tweet::TweetView;
-use crate::context::{Cache, Context};
-use anyhow::{Context as _, Result};
-use chrono::Utc;
+use crate::api::{self, Client, ListId, Page, RateLimited, Timeline};
+use crate::archive::Archive;
+use crate::cache::{Cache, CacheEntry};
+use crate::context::Context;
+use crate::display::{is_broken_pipe, output, stale_notice, Format, OutputOpts, TweetWriter};
+use crate::sub_command::{walk_pages, RangeOpts};
+use crate::utils::time::parse_duration;
+use crate::utils::{color, stdio};
+use anyhow::{bail, Context as _, Result};
 use clap::Clap;
-use colored::{self, Colorize};
-use kuon::{TrimTweet, TwitterAPI};
-use tokio::io::{stdout, AsyncWriteExt, BufWriter, Stdout};
-
-#[derive(Debug)]
-enum DisplayType {
-    Standard,
-    Json,
-    Csv,
-}
-
-impl FromStr for DisplayType {
-    type Err = String;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s.to_lowercase().as_str() {
-            "standard" => Ok(Self::Standard),
-            "json" => Ok(Self::Json),
-            "csv" => Ok(Self::Csv),
-            _ => Err(String::from("no match string")),
-        }
-    }
-}
+use kuon::TrimTweet;
+use std::collections::HashSet;
+use std::time::Duration;
+use tokio::io::{stdout, BufWriter, Stdout};
+use tokio::{signal, time};
 
 #[derive(Debug, Clap)]
 #[clap(name = "tl")]
 pub struct TimeLine {
-    #[clap(long, short, default_value = "standard")]
-    display: DisplayType,
+    #[clap(flatten)]
+    output: OutputOpts,
+    /// With either option, pages are walked until the range is exhausted
+    #[clap(flatten)]
+    range: RangeOpts,
+    /// Tweets mentioning you
+    #[clap(long, conflicts_with_all = &["user", "list", "likes"])]
+    mentions: bool,
+    /// Tweets of a user, such as @name
+    #[clap(long, conflicts_with_all = &["list", "likes"])]
+    user: Option<String>,
+    /// Tweets of a list, as owner/slug or the list id
+    #[clap(long, conflicts_with = "likes")]
+    list: Option<ListId>,
+    /// Tweets liked by you, or by @name
+    #[clap(long)]
+    likes: Option<Option<String>>,
+    /// Same as --since-id
+    #[clap(conflicts_with = "since-id")]
     id: Option<String>,
+    /// Tweets per request (timeline.count in the config by default)
+    #[clap(long)]
+    count: Option<u64>,
+    #[clap(long)]
+    since_id: Option<u64>,
+    /// Only tweets older than or equal to this id
+    #[clap(long)]
+    max_id: Option<u64>,
+    /// Walk back this many pages, printing each as it arrives
+    #[clap(long, conflicts_with = "all")]
+    pages: Option<u32>,
+    /// Walk back until the API returns no more tweets
+    #[clap(long)]
+    all: bool,
+    /// Keep polling and print new tweets oldest first, like tail -f
+    #[clap(long, short, conflicts_with_all = &["max-id", "pages", "all", "since", "until"])]
+    follow: bool,
+    /// Time between polls with --follow, such as 90s or 5m
+    #[clap(long, parse(try_from_str = parse_duration), default_value = "60s")]
+    interval: Duration,
 }
 
 impl TimeLine {
-    pub async fn run(&self, ctx: Context) -> Result<()> {
-        let client = ctx
-            .client
-            .with_context(|| "Please login. run \"petit login\"")?;
-        let tweet_list = if self.id.is_none()
-            && ctx
-                .cache
-                .as_ref()
-                .and_then(|x| x.latest_call)
-                .map(|x| Self::is_latest_request(x))
-                .unwrap_or(false)
-        {
-            ctx.cache
-                .as_ref()
-                .map(|x| x.timeline.clone())
-                .unwrap_or_default()
+    pub async fn run(&self, mut ctx: Context) -> Result<()> {
+        ctx.load_mutes().await?;
+        let since_id = self.range.since_id(
+            self.since_id
+                .or_else(|| self.id.as_ref().and_then(|x| x.parse::<u64>().ok())),
+        );
+        let count = self.count.unwrap_or(ctx.config.timeline.count);
+        let format = self.output.format(&ctx.config)?;
+        let timeline = self.timeline();
+        let mut stdout = BufWriter::new(stdout());
+
+        if self.max_id.is_some() || self.pages.is_some() || self.all || self.range.is_set() {
+            return self
+                .walk(&ctx, &mut stdout, &format, &timeline, since_id, count)
+                .await;
+        }
+        if self.follow {
+            return self
+                .follow(&ctx, &mut stdout, &format, &timeline, since_id, count)
+                .await;
+        }
+
+        let mut tweet_list = if ctx.offline {
+            Self::cached(&ctx, &timeline, since_id)?
         } else {
-            Self::get_tweet(&client, self.id.clone()).await?
+            match Self::fetch(&ctx, &timeline, since_id, count).await {
+                Ok(tweet_list) => tweet_list,
+                Err(err) if api::is_connection_error(&err) => {
+                    eprintln!("{:#}", err);
+                    Self::cached(&ctx, &timeline, since_id)?
+                }
+                Err(err) => return Err(err),
+            }
         };
-        let mut stdout = BufWriter::new(stdout());
-        Self::output(&mut stdout, &tweet_list, &self.display).await?;
+        ctx.mutes.apply(&mut tweet_list);
+        output(&mut stdout, &tweet_list, &format).await?;
 
         Ok(())
     }
 
-    async fn get_tweet(client: &TwitterAPI, since_id: Option<String>) -> Result<Vec<TrimTweet>> {
-        let mut timeline = client.home_timeline();
-        if let Some(id) = since_id.and_then(|x| x.parse::<u64>().ok()) {
-            timeline.since_id(id);
+    /// Pages backwards from `--max-id` or `--until` (or the newest tweet), writing every page
+    /// as soon as it arrives. The cache is left alone; the archive keeps every page.
+    async fn walk(
+        &self,
+        ctx: &Context,
+        stdout: &mut BufWriter<Stdout>,
+        format: &Format,
+        timeline: &Timeline,
+        since_id: Option<u64>,
+        count: u64,
+    ) -> Result<()> {
+        if ctx.offline {
+            let tweet_list = self.walk_offline(ctx, timeline, since_id)?;
+            let mut writer = TweetWriter::begin(stdout, format).await?;
+            writer.write(&tweet_list).await?;
+            return writer.finish().await;
         }
-        let tweet_list = timeline.count(30).send().await?;
-
-        let now = chrono::Utc::now();
-        let cache = Cache {
-            latest_call: Some(now),
-            timeline: tweet_list.clone(),
-            count: 0,
+        let client = ctx.client()?;
+        let writer = TweetWriter::begin(stdout, format).await?;
+        let page = Page {
+            count: Some(count),
+            since_id,
+            max_id: self.range.max_id(self.max_id),
+        };
+        let pages = if self.all || (self.range.is_set() && self.pages.is_none()) {
+            None
+        } else {
+            Some(self.pages.unwrap_or(1))
         };
-        Context::save_cache(&cache).await?;
 
+        walk_pages(ctx, writer, page, pages, |page| async move {
+            client.timeline(timeline, &page).await
+        })
+        .await
+    }
+
+    /// The tweets of a walk that are available offline: the cached ones in the range
+    /// and, for a user, their archived ones.
+    fn walk_offline(
+        &self,
+        ctx: &Context,
+        timeline: &Timeline,
+        since_id: Option<u64>,
+    ) -> Result<Vec<TrimTweet>> {
+        let max_id = self.range.max_id(self.max_id);
+        let entry = ctx
+            .cache
+            .as_ref()
+            .and_then(|x| x.entries.get(&cache_key(timeline)));
+        let mut tweet_list = entry.map(|x| x.timeline.clone()).unwrap_or_default();
+        if let Timeline::User(screen_name) = timeline {
+            tweet_list.extend(Archive::open()?.user_tweets(screen_name, since_id, max_id)?);
+        } else if entry.is_none() {
+            bail!("Offline, and no timeline has been cached yet");
+        }
+        eprintln!(
+            "{}",
+            color::warning(stale_notice(entry.and_then(|x| x.latest_call)))
+        );
+
+        let mut ids = HashSet::new();
+        tweet_list.retain(|x| {
+            since_id.map(|id| x.id > id).unwrap_or(true)
+                && max_id.map(|id| x.id <= id).unwrap_or(true)
+                && ids.insert(x.id)
+        });
+        tweet_list.sort_by_key(|x| std::cmp::Reverse(x.id));
+        ctx.mutes.apply(&mut tweet_list);
         Ok(tweet_list)
     }
 
-    async fn output(
+    /// Polls for tweets newer than the last ones printed and prints them oldest first,
+    /// until interrupted or until the reader of stdout goes away.
+    async fn follow(
+        &self,
+        ctx: &Context,
         stdout: &mut BufWriter<Stdout>,
-        tweet_list: &Vec<TrimTweet>,
-        display: &DisplayType,
+        format: &Format,
+        timeline: &Timeline,
+        mut since_id: Option<u64>,
+        count: u64,
     ) -> Result<()> {
-        match display {
-            &DisplayType::Standard => {
-                let output_line = tweet_list.iter().map(|x| TweetView::from(x)).map(|x| {
-                    format!(
-                        "{} {} {}\n {}\n\n",
-                        x.user_name,
-                        format!("@{}", x.screen_name).bright_red(),
-                        x.retweet_user_name
-                            .map(|x| format!("RT:@{}", x))
-                            .unwrap_or_default()
-                            .bright_green(),
-                        x.tweet,
-                    )
-                });
-                for line in output_line {
-                    stdout.write_all(line.as_bytes()).await?;
+        let client = ctx.client()?;
+        let mut writer = TweetWriter::begin(stdout, format).await?;
+        let interrupted = signal::ctrl_c();
+        tokio::pin!(interrupted);
+        let closed = async {
+            while !stdio::stdout_closed() {
+                time::sleep(Duration::from_millis(500)).await;
+            }
+        };
+        tokio::pin!(closed);
+
+        // Start after the cached tweets, which were printed by an earlier tl.
+        if since_id.is_none() {
+            since_id = ctx
+                .cache
+                .as_ref()
+                .and_then(|x| x.entries.get(&cache_key(timeline)))
+                .and_then(CacheEntry::newest_id);
+        }
+        let mut wait = Duration::from_secs(0);
+        loop {
+            let result = tokio::select! {
+                _ = &mut interrupted => break,
+                _ = &mut closed => return Ok(()),
+                result = async {
+                    time::sleep(wait).await;
+                    Self::fetch(ctx, timeline, since_id, count).await
+                } => result,
+            };
+            // Slow down when polling at the interval would use up the rate limit early.
+            wait = match client.rate_limit().and_then(|x| x.pace()) {
+                Some(pace) => pace.max(self.interval),
+                None => self.interval,
+            };
+
+            let mut tweet_list = match result {
+                Ok(tweet_list) => tweet_list,
+                Err(err) if api::is_connection_error(&err) => {
+                    eprintln!("{:#}", err);
+                    continue;
                 }
+                Err(err) => match err.downcast_ref::<RateLimited>() {
+                    Some(limited) => {
+                        eprintln!("{}", limited);
+                        wait = limited.wait().map_or(wait, |x| x.max(wait));
+                        continue;
+                    }
+                    None => return Err(err),
+                },
+            };
+            if let Some(newest) = tweet_list.first() {
+                since_id = Some(newest.id);
+            }
+            ctx.mutes.apply(&mut tweet_list);
+            tweet_list.reverse();
+            match writer.write(&tweet_list).await {
+                Err(err) if is_broken_pipe(&err) => return Ok(()),
+                result => result?,
             }
-            &DisplayType::Json => {
-                let json = serde_json::to_string(&tweet_list)?;
-                stdout.write_all(json.as_bytes()).await?;
+        }
+
+        match writer.finish().await {
+            Err(err) if is_broken_pipe(&err) => Ok(()),
+            result => result,
+        }
+    }
+
+    async fn fetch(
+        ctx: &Context,
+        timeline: &Timeline,
+        since_id: Option<u64>,
+        count: u64,
+    ) -> Result<Vec<TrimTweet>> {
+        let client = ctx.client()?;
+        let config = &ctx.config;
+        let account = ctx.account.clone();
+        let limit = config.cache.limit;
+        let key = cache_key(timeline);
+
+        let cached = match since_id {
+            Some(_) => None,
+            None => Cache::hit(account.clone(), &key, config.cache.ttl(timeline.name())).await?,
+        };
+        if let Some(entry) = cached {
+            return Ok(entry.timeline);
+        }
+
+        // Read again, since `--follow` adds to the cache after startup.
+        let newest_id = Cache::load(account.as_deref())
+            .await?
+            .and_then(|mut x| x.entries.remove(&key))
+            .and_then(|x| x.newest_id());
+        let (tweet_list, gap_filled) =
+            Self::get_tweet(client, timeline, since_id, newest_id, count, limit).await?;
+        Archive::keep(&tweet_list);
+        let entry = Cache::store(account, &key, |entry| {
+            // Tweets between the fetched ones and the cached ones are missing,
+            // so a merged timeline would silently skip them.
+            if !gap_filled {
+                entry.timeline.clear();
             }
-            &DisplayType::Csv => {
-                let output_line = tweet_list.iter().map(|x| TweetView::from(x)).map(|x| {
-                    format!(
-                        "{}\t{}\t{}\t{}{}\n",
-                        x.id,
-                        x.user_name,
-                        x.screen_name,
-                        x.tweet.replace("\n", " "),
-                        x.retweet_user_name
-                            .as_ref()
-                            .map(|x| String::from("\t") + x)
-                            .unwrap_or_default()
-                    )
-                });
-                for line in output_line {
-                    stdout.write_all(line.as_bytes()).await?;
+            entry.merge(tweet_list.clone(), limit)
+        })
+        .await?;
+
+        Ok(match since_id {
+            Some(since_id) => tweet_list.into_iter().filter(|x| x.id > since_id).collect(),
+            None => entry.timeline,
+        })
+    }
+
+    /// Serves the cached timeline without calling the API, however old it is.
+    fn cached(ctx: &Context, timeline: &Timeline, since_id: Option<u64>) -> Result<Vec<TrimTweet>> {
+        let entry = ctx
+            .cache
+            .as_ref()
+            .and_then(|x| x.entries.get(&cache_key(timeline)))
+            .with_context(|| "Offline, and no timeline has been cached yet")?;
+        eprintln!("{}", color::warning(stale_notice(entry.latest_call)));
+
+        Ok(entry
+            .timeline
+            .iter()
+            .filter(|x| since_id.map(|id| x.id > id).unwrap_or(true))
+            .cloned()
+            .collect())
+    }
+
+    /// Fetches the tweets newer than `since_id`, or the newest page without it, along
+    /// with whether they reach the cached tweets, whose newest is `newest_id`.
+    ///
+    /// Pages never tell that the gap is filled by their size, since the API drops
+    /// deleted tweets after applying `count`. So one cached tweet is fetched again,
+    /// and the gap is filled when it comes back. Older pages are followed until then,
+    /// or down to a `since_id` below the cache, or until `limit` tweets are fetched.
+    /// The tweets returned may go back further than `since_id`.
+    async fn get_tweet(
+        client: &Client,
+        timeline: &Timeline,
+        since_id: Option<u64>,
+        newest_id: Option<u64>,
+        count: u64,
+        limit: usize,
+    ) -> Result<(Vec<TrimTweet>, bool)> {
+        let below_cache = matches!((since_id, newest_id), (Some(s), Some(n)) if s < n);
+        let mut page = Page {
+            count: Some(count),
+            since_id: match newest_id {
+                Some(_) if below_cache => since_id,
+                Some(newest_id) => newest_id.checked_sub(1),
+                None => since_id,
+            },
+            ..Default::default()
+        };
+        let mut tweet_list = Vec::new();
+        let mut gap_filled = newest_id.is_none();
+
+        loop {
+            let tweets = client.timeline(timeline, &page).await?;
+            gap_filled |= tweets.iter().any(|x| Some(x.id) == newest_id);
+            let oldest = tweets.last().map(|x| x.id);
+            tweet_list.extend(tweets);
+
+            let reached = match newest_id {
+                Some(_) => gap_filled && !below_cache,
+                None => since_id.is_none(),
+            };
+            match oldest.and_then(|x| x.checked_sub(1)) {
+                Some(id) if page.since_id.is_some() && !reached && tweet_list.len() < limit => {
+                    page.max_id = Some(id)
                 }
+                _ => break,
             }
         }
 
-        stdout.flush().await.with_context(|| "Output Error")
+        Ok((tweet_list, gap_filled))
     }
 
-    fn is_latest_request(latest_call: chrono::DateTime<Utc>) -> bool {
-        let now = Utc::now();
-        let delta = now - latest_call;
-
-        delta.num_minutes() < 1
+    fn timeline(&self) -> Timeline {
+        let screen_name = |x: &String| x.trim_start_matches('@').to_string();
+        if self.mentions {
+            Timeline::Mentions
+        } else if let Some(user) = &self.user {
+            Timeline::User(screen_name(user))
+        } else if let Some(list) = &self.list {
+            Timeline::List(list.clone())
+        } else if let Some(likes) = &self.likes {
+            Timeline::Likes(likes.as_ref().map(screen_name))
+        } else {
+            Timeline::Home
+        }
     }
 }
+
+/// Such as `home`, `user?screen_name=alice` or `list?owner_screen_name=alice&slug=rust`.
+fn cache_key(timeline: &Timeline) -> String {
+    let params = timeline.params();
+    let params = params
+        .iter()
+        .map(|(k, v)| (*k, v.as_str()))
+        .collect::<Vec<_>>();
+    Cache::key(timeline.name(), &params)
+}
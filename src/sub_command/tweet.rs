@@ -3,6 +3,7 @@ use clap::Clap;
 use tokio::io::{stdout, AsyncWriteExt, BufWriter};
 
 use crate::context::Context;
+use crate::outbox::{self, Outbox, Rejected};
 
 #[derive(Debug, Clap)]
 pub struct Tweet {
@@ -13,13 +14,83 @@ impl Tweet {
     pub async fn run(&self, ctx: Context) -> Result<()> {
         let mut stdout = BufWriter::new(stdout());
 
-        let client = ctx
-            .client
-            .with_context(|| "Please login. run \"kuon login\"")?;
+        let account = ctx
+            .account
+            .clone()
+            .with_context(|| "Please login. run \"petit login\"")?;
+        if ctx.offline {
+            return Self::queue(&mut stdout, &account, &self.content).await;
+        }
+        let client = ctx.client()?;
 
-        client.tweet().status(&self.content).send().await?;
-        stdout.write_all(b"success!").await?;
+        // Queued tweets go out first to keep the order they were written in.
+        let mut rejected = None;
+        let blocked = match Outbox::send(client, &account).await {
+            Ok(sent) => {
+                Self::report_sent(&mut stdout, sent).await?;
+                false
+            }
+            Err(err) => match err.downcast::<Rejected>() {
+                // Why the rest stopped is part of the error reported at the end.
+                Ok(x) => {
+                    Self::report_sent(&mut stdout, x.sent).await?;
+                    let blocked = x.stopped.is_some();
+                    rejected = Some(x);
+                    blocked
+                }
+                Err(err) if outbox::is_temporary(&err) => {
+                    eprintln!("{:#}", err);
+                    true
+                }
+                Err(err) => return Err(err),
+            },
+        };
 
+        // The new tweet waits behind queued tweets that cannot be sent yet.
+        if blocked {
+            Self::queue(&mut stdout, &account, &self.content).await?;
+        } else {
+            match client.tweet(&self.content).await {
+                Ok(_) => {
+                    stdout.write_all(b"success!").await?;
+                    stdout.flush().await?;
+                }
+                Err(err) if outbox::is_temporary(&err) => {
+                    eprintln!("{:#}", err);
+                    Self::queue(&mut stdout, &account, &self.content).await?;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        match rejected {
+            Some(rejected) => Err(rejected.into()),
+            None => Ok(()),
+        }
+    }
+
+    async fn report_sent(stdout: &mut BufWriter<tokio::io::Stdout>, sent: usize) -> Result<()> {
+        if sent > 0 {
+            stdout
+                .write_all(format!("Sent {} queued tweets\n", sent).as_bytes())
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn queue(
+        stdout: &mut BufWriter<tokio::io::Stdout>,
+        account: &str,
+        content: &str,
+    ) -> Result<()> {
+        Outbox::push(account, content).await?;
+        stdout
+            .write_all(
+                b"Queued the tweet. It is sent by the next online \"petit tweet\" \
+                  or \"petit outbox send\"\n",
+            )
+            .await?;
+        stdout.flush().await?;
         Ok(())
     }
 }
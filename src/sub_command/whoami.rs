@@ -0,0 +1,52 @@
+use anyhow::{bail, Result};
+use clap::Clap;
+use tokio::io::{stdout, AsyncWriteExt, BufWriter};
+
+use crate::context::Context;
+use crate::display::DisplayType;
+
+#[derive(Debug, Clap)]
+pub struct WhoAmI {
+    #[clap(long, short, default_value = "standard")]
+    display: DisplayType,
+}
+
+impl WhoAmI {
+    pub async fn run(&self, ctx: Context) -> Result<()> {
+        let client = ctx.client()?;
+        let account = ctx.account.clone().unwrap_or_default();
+
+        let user = client.verify_credentials().await?;
+
+        let mut stdout = BufWriter::new(stdout());
+        match self.display {
+            DisplayType::Standard => {
+                let text = format!(
+                    "{} @{}\nid: {}\nfollowers: {}\nfollowing: {}\naccount: {}\n",
+                    user.name,
+                    user.screen_name,
+                    user.id_str,
+                    user.followers_count,
+                    user.friends_count,
+                    account
+                );
+                stdout.write_all(text.as_bytes()).await?;
+            }
+            DisplayType::Json => {
+                let json = serde_json::json!({
+                    "account": account,
+                    "id": user.id_str,
+                    "screen_name": user.screen_name,
+                    "name": user.name,
+                    "followers_count": user.followers_count,
+                    "friends_count": user.friends_count,
+                });
+                stdout.write_all(json.to_string().as_bytes()).await?;
+            }
+            display => bail!("whoami does not support {}", display.as_str()),
+        }
+
+        stdout.flush().await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,274 @@
+use anyhow::{bail, Result};
+use chrono::format::{Item, StrftimeItems};
+use chrono::Utc;
+use colored::{Color, ColoredString, Colorize};
+use kuon::TrimTweet;
+use std::str::FromStr;
+
+use crate::component::tweet::TweetView;
+use crate::config::TimeConfig;
+
+/// A user-defined line format such as `{created_at:%H:%M} @{screen_name}: {text}`.
+///
+/// `{field}` and `{field:spec}` insert a tweet field, `{red}`, `{bright_green}`,
+/// `{bold}` and the like style the text that follows them until `{reset}`,
+/// `{{` and `}}` are literal braces, and `\n` and `\t` are a line break and a tab.
+#[derive(Debug, Clone)]
+pub struct Template {
+    pieces: Vec<Piece>,
+}
+
+#[derive(Debug, Clone)]
+enum Piece {
+    Literal(String),
+    Field(Field),
+    Style(Directive),
+}
+
+#[derive(Debug, Clone)]
+enum Field {
+    Id,
+    /// The posting time, in the given strftime format or the configured one.
+    CreatedAt(Option<String>),
+    /// How long ago the tweet was posted, such as `3m` or `2d`.
+    Age,
+    UserName,
+    ScreenName,
+    Text,
+    RetweetedBy,
+    RetweetCount,
+    FavoriteCount,
+    Url,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Directive {
+    Color(Color),
+    Bold,
+    Dimmed,
+    Italic,
+    Underline,
+    Reset,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Style {
+    color: Option<Color>,
+    bold: bool,
+    dimmed: bool,
+    italic: bool,
+    underline: bool,
+}
+
+pub const FIELDS: [&str; 10] = [
+    "id",
+    "created_at",
+    "age",
+    "user_name",
+    "screen_name",
+    "text",
+    "retweeted_by",
+    "retweet_count",
+    "favorite_count",
+    "url",
+];
+
+impl FromStr for Template {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut pieces = Vec::new();
+        let mut literal = String::new();
+        let mut chars = s.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '\\' => match chars.next() {
+                    Some('n') => literal.push('\n'),
+                    Some('t') => literal.push('\t'),
+                    Some(other) => {
+                        literal.push('\\');
+                        literal.push(other);
+                    }
+                    None => literal.push('\\'),
+                },
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => bail!("unclosed {{ in format: {}", s),
+                        }
+                    }
+                    if !literal.is_empty() {
+                        pieces.push(Piece::Literal(std::mem::take(&mut literal)));
+                    }
+                    pieces.push(Piece::parse(&name)?);
+                }
+                '}' => bail!(
+                    "unmatched }} in format: {} (write }}}} for a literal brace)",
+                    s
+                ),
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            pieces.push(Piece::Literal(literal));
+        }
+
+        Ok(Self { pieces })
+    }
+}
+
+impl Piece {
+    fn parse(name: &str) -> Result<Self> {
+        let (name, spec) = match name.find(':') {
+            Some(i) => (&name[..i], Some(&name[i + 1..])),
+            None => (name, None),
+        };
+        let field = match name {
+            "id" => Field::Id,
+            "created_at" => {
+                if let Some(format) = spec {
+                    if StrftimeItems::new(format).any(|x| x == Item::Error) {
+                        bail!("invalid time format: {}", format);
+                    }
+                }
+                return Ok(Self::Field(Field::CreatedAt(spec.map(String::from))));
+            }
+            "age" => Field::Age,
+            "user_name" | "user" => Field::UserName,
+            "screen_name" => Field::ScreenName,
+            "text" => Field::Text,
+            "retweeted_by" => Field::RetweetedBy,
+            "retweet_count" => Field::RetweetCount,
+            "favorite_count" => Field::FavoriteCount,
+            "url" => Field::Url,
+            _ => return Directive::parse(name).map(Self::Style),
+        };
+        if spec.is_some() {
+            bail!("only created_at takes a format: {{{}}}", name);
+        }
+
+        Ok(Self::Field(field))
+    }
+}
+
+impl Directive {
+    fn parse(name: &str) -> Result<Self> {
+        let directive = match name {
+            "bold" => Self::Bold,
+            "dimmed" => Self::Dimmed,
+            "italic" => Self::Italic,
+            "underline" => Self::Underline,
+            "reset" => Self::Reset,
+            _ => match Color::from_str(&name.replace('_', " ")) {
+                Ok(color) => Self::Color(color),
+                Err(_) => bail!(
+                    "unknown field or style in format: {{{}}} (fields: {})",
+                    name,
+                    FIELDS.join(", ")
+                ),
+            },
+        };
+        Ok(directive)
+    }
+}
+
+impl Style {
+    fn apply(mut self, directive: Directive) -> Self {
+        match directive {
+            Directive::Color(color) => self.color = Some(color),
+            Directive::Bold => self.bold = true,
+            Directive::Dimmed => self.dimmed = true,
+            Directive::Italic => self.italic = true,
+            Directive::Underline => self.underline = true,
+            Directive::Reset => self = Self::default(),
+        }
+        self
+    }
+
+    fn paint(&self, text: &str) -> ColoredString {
+        let mut painted = ColoredString::from(text);
+        if let Some(color) = self.color {
+            painted = painted.color(color);
+        }
+        if self.bold {
+            painted = painted.bold();
+        }
+        if self.dimmed {
+            painted = painted.dimmed();
+        }
+        if self.italic {
+            painted = painted.italic();
+        }
+        if self.underline {
+            painted = painted.underline();
+        }
+        painted
+    }
+}
+
+impl Template {
+    /// Renders one tweet, without a trailing line break.
+    pub fn render(&self, tweet: &TrimTweet, time: &TimeConfig) -> String {
+        let view = TweetView::from(tweet);
+        let mut style = Style::default();
+        let mut line = String::new();
+
+        for piece in &self.pieces {
+            let text = match piece {
+                Piece::Literal(text) => text.clone(),
+                Piece::Field(field) => field.value(&view, time),
+                Piece::Style(directive) => {
+                    style = style.apply(*directive);
+                    continue;
+                }
+            };
+            line.push_str(&style.paint(&text).to_string());
+        }
+        line
+    }
+}
+
+impl Field {
+    fn value(&self, view: &TweetView, time: &TimeConfig) -> String {
+        match self {
+            Self::Id => view.id.clone(),
+            Self::CreatedAt(format) => view
+                .created_at
+                .map(|x| match format {
+                    Some(format) => time.timezone.format(x, format),
+                    None => time.absolute(x),
+                })
+                .unwrap_or_default(),
+            Self::Age => view
+                .created_at
+                .map(|x| crate::utils::time::relative(Utc::now() - x))
+                .unwrap_or_default(),
+            Self::UserName => view.user_name.clone(),
+            Self::ScreenName => view.screen_name.clone(),
+            Self::Text => view.tweet.clone(),
+            Self::RetweetedBy => view.retweet_user_name.clone().unwrap_or_default(),
+            Self::RetweetCount => view.retweet_count.to_string(),
+            Self::FavoriteCount => view.favorite_count.to_string(),
+            Self::Url => tweet_url(view),
+        }
+    }
+}
+
+pub fn tweet_url(view: &TweetView) -> String {
+    format!(
+        "https://twitter.com/{}/status/{}",
+        view.screen_name, view.id
+    )
+}
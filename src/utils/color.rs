@@ -0,0 +1,75 @@
+use colored::control;
+use std::env;
+use std::fmt::Display;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static STDERR_COLOR: AtomicBool = AtomicBool::new(false);
+
+/// When escapes for colors and styles are written.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColorChoice {
+    /// When the output is a terminal, unless NO_COLOR is set or CLICOLOR is 0.
+    /// CLICOLOR_FORCE other than 0 colors output that is not a terminal, but
+    /// NO_COLOR still wins.
+    Auto,
+    Always,
+    Never,
+}
+
+impl FromStr for ColorChoice {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "auto" => Ok(Self::Auto),
+            "always" => Ok(Self::Always),
+            "never" => Ok(Self::Never),
+            _ => Err(String::from("use auto, always or never")),
+        }
+    }
+}
+
+impl ColorChoice {
+    /// Decides coloring for stdout and stderr, each by whether it is a terminal.
+    pub fn apply(self) {
+        control::set_override(self.enabled(termion::is_tty(&std::io::stdout())));
+        STDERR_COLOR.store(
+            self.enabled(termion::is_tty(&std::io::stderr())),
+            Ordering::Relaxed,
+        );
+    }
+
+    fn enabled(self, is_tty: bool) -> bool {
+        let set = |name| env::var_os(name).map(|x| !x.is_empty()).unwrap_or(false);
+        let zero = |name| env::var_os(name).map(|x| x == "0").unwrap_or(false);
+        match self {
+            Self::Always => true,
+            Self::Never => false,
+            Self::Auto if set("NO_COLOR") => false,
+            Self::Auto if set("CLICOLOR_FORCE") && !zero("CLICOLOR_FORCE") => true,
+            Self::Auto if zero("CLICOLOR") => false,
+            Self::Auto => is_tty,
+        }
+    }
+}
+
+/// Yellow text for stderr, such as a notice that shown data is stale.
+pub fn warning(text: impl Display) -> String {
+    paint(text, "33")
+}
+
+/// Bold red text for stderr.
+pub fn error(text: impl Display) -> String {
+    paint(text, "1;31")
+}
+
+/// Wraps `text` in the escapes for `sgr` when stderr is to be colored. The escapes
+/// are written by hand, because `colored` has a single switch, which follows stdout.
+fn paint(text: impl Display, sgr: &str) -> String {
+    if STDERR_COLOR.load(Ordering::Relaxed) {
+        format!("\x1b[{}m{}\x1b[0m", sgr, text)
+    } else {
+        text.to_string()
+    }
+}
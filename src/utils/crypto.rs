@@ -0,0 +1,83 @@
+use anyhow::{anyhow, bail, Context as _, Result};
+use chacha20poly1305::aead::{Aead, NewAead};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use hmac::Hmac;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::convert::TryInto;
+use std::io::{stderr, stdin, Write};
+use termion::input::TermRead;
+
+const VERSION: u32 = 1;
+const ITERATIONS: u32 = 100_000;
+
+/// Data sealed with a key derived from a passphrase.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Encrypted {
+    pub version: u32,
+    pub iterations: u32,
+    pub salt: String,
+    pub nonce: String,
+    pub data: String,
+}
+
+fn derive_key(passphrase: &str, salt: &[u8], iterations: u32) -> Key {
+    let mut key = Key::default();
+    pbkdf2::pbkdf2::<Hmac<Sha256>>(passphrase.as_bytes(), salt, iterations, &mut key);
+    key
+}
+
+pub fn encrypt(passphrase: &str, plaintext: &[u8]) -> Result<Encrypted> {
+    let mut salt = [0u8; 16];
+    let mut nonce = [0u8; 12];
+    rand::thread_rng().fill_bytes(&mut salt);
+    rand::thread_rng().fill_bytes(&mut nonce);
+
+    let cipher = ChaCha20Poly1305::new(&derive_key(passphrase, &salt, ITERATIONS));
+    let data = cipher
+        .encrypt(&Nonce::from(nonce), plaintext)
+        .map_err(|_| anyhow!("Failed to encrypt"))?;
+
+    Ok(Encrypted {
+        version: VERSION,
+        iterations: ITERATIONS,
+        salt: base64::encode(salt),
+        nonce: base64::encode(nonce),
+        data: base64::encode(data),
+    })
+}
+
+pub fn decrypt(passphrase: &str, encrypted: &Encrypted) -> Result<Vec<u8>> {
+    if encrypted.version != VERSION {
+        bail!("Unsupported encryption version: {}", encrypted.version);
+    }
+    let salt = base64::decode(&encrypted.salt)?;
+    let nonce: [u8; 12] = base64::decode(&encrypted.nonce)?
+        .as_slice()
+        .try_into()
+        .map_err(|_| anyhow!("Broken encrypted data"))?;
+    let data = base64::decode(&encrypted.data)?;
+
+    let cipher = ChaCha20Poly1305::new(&derive_key(passphrase, &salt, encrypted.iterations));
+    cipher
+        .decrypt(&Nonce::from(nonce), data.as_ref())
+        .map_err(|_| anyhow!("Wrong passphrase"))
+}
+
+/// Reads the passphrase from `PETIT_PASSPHRASE`, or asks for it on the terminal.
+pub fn passphrase(prompt: &str) -> Result<String> {
+    if let Ok(passphrase) = std::env::var("PETIT_PASSPHRASE") {
+        return Ok(passphrase);
+    }
+
+    let mut stderr = stderr();
+    stderr.write_all(prompt.as_bytes())?;
+    stderr.flush()?;
+    let passphrase = stdin()
+        .read_passwd(&mut stderr)?
+        .with_context(|| "No passphrase given")?;
+    stderr.write_all(b"\n")?;
+
+    Ok(passphrase)
+}
@@ -0,0 +1,109 @@
+use anyhow::{Context as _, Result};
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+const APP_NAME: &str = "petit";
+
+fn home_dir() -> Result<PathBuf> {
+    std::env::var_os("HOME")
+        .filter(|x| !x.is_empty())
+        .map(PathBuf::from)
+        .with_context(|| "HOME is not set")
+}
+
+/// Resolves `$<var>/petit`, falling back to `$HOME/<fallback>/petit` when the
+/// variable is unset or not an absolute path, as the XDG spec requires.
+fn xdg_dir(var: &str, fallback: &str) -> Result<PathBuf> {
+    let base = match std::env::var_os(var).map(PathBuf::from) {
+        Some(path) if path.is_absolute() => path,
+        _ => home_dir()?.join(fallback),
+    };
+    Ok(base.join(APP_NAME))
+}
+
+pub fn config_dir() -> Result<PathBuf> {
+    xdg_dir("XDG_CONFIG_HOME", ".config")
+}
+
+pub fn cache_dir() -> Result<PathBuf> {
+    xdg_dir("XDG_CACHE_HOME", ".cache")
+}
+
+pub fn data_dir() -> Result<PathBuf> {
+    xdg_dir("XDG_DATA_HOME", ".local/share")
+}
+
+/// Moves files written by older versions into the XDG directories and
+/// creates any directory that is still missing.
+pub async fn prepare(token_path: &Path, cache_path: &Path) -> Result<()> {
+    let home_dir = home_dir()?;
+
+    let legacy_cache = home_dir.join(".cache").join(APP_NAME);
+    if is_file(&legacy_cache).await {
+        // The legacy cache file usually sits where the cache directory goes,
+        // so it has to be moved aside before the directory can be created.
+        let moving = legacy_cache.with_extension("migrating");
+        fs::rename(&legacy_cache, &moving).await?;
+        migrate(&moving, cache_path).await?;
+        // Left behind only when a cache already exists at the new location.
+        let _ = fs::remove_file(&moving).await;
+    }
+
+    let legacy_token = home_dir.join(".petit");
+    if is_file(&legacy_token).await {
+        migrate(&legacy_token, token_path).await?;
+    }
+
+    for dir in [config_dir()?, cache_dir()?].iter() {
+        fs::create_dir_all(dir)
+            .await
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    create_private_dir(&data_dir()?).await?;
+
+    Ok(())
+}
+
+async fn migrate(from: &Path, to: &Path) -> Result<()> {
+    if is_file(to).await {
+        return Ok(());
+    }
+    if let Some(parent) = to.parent() {
+        if parent.starts_with(data_dir()?) {
+            create_private_dir(parent).await?;
+        } else {
+            fs::create_dir_all(parent).await?;
+        }
+    }
+    if fs::rename(from, to).await.is_err() {
+        // rename does not work across file systems
+        fs::copy(from, to)
+            .await
+            .with_context(|| format!("Failed to move {} to {}", from.display(), to.display()))?;
+        fs::remove_file(from).await?;
+    }
+    // ~/.petit was written with the default umask.
+    super::file::make_private(to)?;
+
+    Ok(())
+}
+
+/// Creates `dir` (and missing parents) so that only the current user can enter
+/// it, since it holds the account tokens.
+async fn create_private_dir(dir: &Path) -> Result<()> {
+    let mut builder = fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    builder.mode(0o700);
+    builder
+        .create(dir)
+        .await
+        .with_context(|| format!("Failed to create {}", dir.display()))
+}
+
+async fn is_file(path: &Path) -> bool {
+    fs::metadata(path)
+        .await
+        .map(|x| x.is_file())
+        .unwrap_or(false)
+}
@@ -20,19 +20,17 @@ pub struct Events {
 }
 
 impl Events {
-    pub fn new() -> Self {
+    pub fn new(tick_rate: Duration) -> Self {
         let (tx, rx) = mpsc::channel(2);
         let _input_handle = {
             let tx = tx.clone();
             tokio::spawn(async move {
                 let stdin = io::stdin();
 
-                for i in stdin.keys() {
-                    if let Ok(key) = i {
-                        if let Err(err) = tx.send(Event::Input(key)).await {
-                            eprintln!("{}", err);
-                            break;
-                        }
+                for key in stdin.keys().flatten() {
+                    if let Err(err) = tx.send(Event::Input(key)).await {
+                        eprintln!("{}", err);
+                        break;
                     }
                 }
             })
@@ -45,7 +43,7 @@ impl Events {
                     if tx.send(Event::Tick).await.is_err() {
                         break;
                     }
-                    time::sleep(Duration::from_millis(1000)).await;
+                    time::sleep(tick_rate).await;
                 }
             })
         };
@@ -0,0 +1,74 @@
+use anyhow::{Context as _, Result};
+use fs2::FileExt;
+use std::path::Path;
+use tokio::fs::{self, File, OpenOptions};
+use tokio::io::{AsyncWriteExt, BufWriter};
+
+/// Creates or truncates a file that only the current user can read and write.
+pub async fn create_private(path: &Path) -> Result<File> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+    let file = options.open(path).await?;
+
+    // `mode` only applies to new files, so tighten files created by older versions too.
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(std::fs::Permissions::from_mode(0o600))
+            .await?;
+    }
+
+    Ok(file)
+}
+
+/// Replaces `path` with `data` through a temporary file and a rename, so that
+/// readers see either the old or the new content and never a partial write.
+pub async fn write_private(path: &Path, data: &[u8]) -> Result<()> {
+    let tmp = path.with_extension(format!("tmp.{}", std::process::id()));
+    let result = async {
+        let mut writer = BufWriter::new(create_private(&tmp).await?);
+        writer.write_all(data).await?;
+        writer.flush().await?;
+        writer.get_ref().sync_all().await?;
+        fs::rename(&tmp, path).await?;
+        Ok(())
+    }
+    .await;
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp).await;
+    }
+    result
+}
+
+/// Restricts an existing file to the current user.
+pub fn make_private(path: &Path) -> Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+
+    Ok(())
+}
+
+/// Takes an exclusive lock on `path`, waiting for other petit runs that hold it.
+/// The lock is released when the returned file is dropped.
+pub async fn lock(path: &Path) -> Result<std::fs::File> {
+    let path = path.to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(false)
+            .open(&path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        file.lock_exclusive()
+            .with_context(|| format!("Failed to lock {}", path.display()))?;
+        Ok(file)
+    })
+    .await?
+}
@@ -13,13 +13,13 @@ pub enum Size {
     Auto,
 }
 
-impl Size {
-    pub fn to_string(&self) -> String {
+impl std::fmt::Display for Size {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Size::Char(n) => format!("{}", n),
-            Size::Px(n) => format!("{}px", n),
-            Size::Percent(n) => format!("{}%", n),
-            Size::Auto => String::from("auto"),
+            Size::Char(n) => write!(f, "{}", n),
+            Size::Px(n) => write!(f, "{}px", n),
+            Size::Percent(n) => write!(f, "{}%", n),
+            Size::Auto => write!(f, "auto"),
         }
     }
 }
@@ -70,8 +70,8 @@ impl Image {
         let args = format!(
             "size={};width={};height={};inline={}",
             self.img.len(),
-            config.width.to_string(),
-            config.height.to_string(),
+            config.width,
+            config.height,
             1
         );
 
@@ -1,4 +1,9 @@
+pub mod color;
+pub mod crypto;
+pub mod dirs;
 pub mod event;
+pub mod file;
 pub mod img;
 pub mod stdio;
 pub mod terminal;
+pub mod time;
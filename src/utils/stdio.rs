@@ -15,3 +15,22 @@ pub fn read_stdin() -> Result<String> {
     stdin.read_line(&mut buf)?;
     Ok(buf)
 }
+
+/// Whether the reader of stdout has gone away, such as `head` after its last line.
+/// It is checked without writing, so that a quiet `tl --follow` notices it too.
+#[cfg(unix)]
+pub fn stdout_closed() -> bool {
+    let mut fd = libc::pollfd {
+        fd: libc::STDOUT_FILENO,
+        events: 0,
+        revents: 0,
+    };
+    // SAFETY: `fd` is a single valid pollfd, and a timeout of 0 does not block.
+    let ready = unsafe { libc::poll(&mut fd, 1, 0) };
+    ready > 0 && fd.revents & (libc::POLLERR | libc::POLLHUP) != 0
+}
+
+#[cfg(not(unix))]
+pub fn stdout_closed() -> bool {
+    false
+}
@@ -7,8 +7,9 @@ use termion::{
 };
 use tui::{backend::TermionBackend, Terminal};
 
-pub fn create_terminal(
-) -> Result<Terminal<TermionBackend<AlternateScreen<MouseTerminal<RawTerminal<Stdout>>>>>> {
+pub type Backend = TermionBackend<AlternateScreen<MouseTerminal<RawTerminal<Stdout>>>>;
+
+pub fn create_terminal() -> Result<Terminal<Backend>> {
     let stdout = stdout().into_raw_mode()?;
     let stdout = MouseTerminal::from(stdout);
     let stdout = AlternateScreen::from(stdout);
@@ -0,0 +1,132 @@
+use anyhow::{bail, Context as _, Result};
+use chrono::{DateTime, FixedOffset, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Parses durations such as `90`, `90s`, `5m`, `2h` or `1d`. A bare number is seconds.
+pub fn parse_duration(s: &str) -> Result<Duration> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let number = number
+        .parse::<u64>()
+        .with_context(|| format!("invalid duration: {}", s))?;
+    let seconds = match unit {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => bail!("invalid duration: {} (use s, m, h or d)", s),
+    };
+
+    number
+        .checked_mul(seconds)
+        .map(Duration::from_secs)
+        .with_context(|| format!("invalid duration: {} (too long)", s))
+}
+
+/// Parses an RFC 3339 time such as `2021-03-06T12:00:00Z`, or a local date
+/// such as `2021-03-06` (its midnight) or date and time `2021-03-06 12:00`.
+pub fn parse_datetime(s: &str) -> Result<DateTime<Utc>> {
+    let s = s.trim();
+    if let Ok(time) = DateTime::parse_from_rfc3339(s) {
+        return Ok(time.with_timezone(&Utc));
+    }
+    let local = NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M")
+        .or_else(|_| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S"))
+        .or_else(|_| NaiveDate::parse_from_str(s, "%Y-%m-%d").map(|x| x.and_hms(0, 0, 0)))
+        .with_context(|| {
+            format!(
+                "invalid time: {} (use 2021-03-06, 2021-03-06 12:00 or RFC 3339)",
+                s
+            )
+        })?;
+    match Local.from_local_datetime(&local).earliest() {
+        Some(time) => Ok(time.with_timezone(&Utc)),
+        None => bail!("{} does not exist in the local time zone", s),
+    }
+}
+
+/// Parses a time as [`parse_datetime`] does, or a duration such as `2h` or `7d`
+/// as that long before now.
+pub fn parse_time_or_ago(s: &str) -> Result<DateTime<Utc>> {
+    if let Ok(time) = parse_datetime(s) {
+        return Ok(time);
+    }
+    let ago = parse_duration(s)
+        .ok()
+        .and_then(|x| chrono::Duration::from_std(x).ok())
+        .with_context(|| {
+            format!(
+                "invalid time: {} (use 2021-03-06, 2021-03-06 12:00, RFC 3339 or a duration such as 2h)",
+                s
+            )
+        })?;
+    Ok(Utc::now() - ago)
+}
+
+/// The time zone times are shown in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Zone {
+    Local,
+    Utc,
+    Fixed(FixedOffset),
+}
+
+impl Zone {
+    pub fn format(&self, time: DateTime<Utc>, format: &str) -> String {
+        match self {
+            Self::Local => time.with_timezone(&Local).format(format).to_string(),
+            Self::Utc => time.format(format).to_string(),
+            Self::Fixed(offset) => time.with_timezone(offset).format(format).to_string(),
+        }
+    }
+
+    pub fn rfc3339(&self, time: DateTime<Utc>) -> String {
+        match self {
+            Self::Local => time.with_timezone(&Local).to_rfc3339(),
+            Self::Utc => time.to_rfc3339(),
+            Self::Fixed(offset) => time.with_timezone(offset).to_rfc3339(),
+        }
+    }
+}
+
+impl FromStr for Zone {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "local" => Ok(Self::Local),
+            "utc" | "z" => Ok(Self::Utc),
+            offset => {
+                // Reuse the RFC 3339 parser for offsets such as +09:00.
+                let time = format!("2000-01-01T00:00:00{}", offset);
+                DateTime::parse_from_rfc3339(&time)
+                    .map(|x| Self::Fixed(*x.offset()))
+                    .with_context(|| format!("unknown time zone: {} (use local, utc or +09:00)", s))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Zone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Local => f.write_str("local"),
+            Self::Utc => f.write_str("utc"),
+            Self::Fixed(offset) => write!(f, "{}", offset),
+        }
+    }
+}
+
+/// Shortens an elapsed time to its largest unit, such as `45s`, `3m`, `2h` or `5d`.
+pub fn relative(elapsed: chrono::Duration) -> String {
+    let seconds = elapsed.num_seconds().max(0);
+    match seconds {
+        s if s < 60 => format!("{}s", s),
+        s if s < 60 * 60 => format!("{}m", s / 60),
+        s if s < 24 * 60 * 60 => format!("{}h", s / (60 * 60)),
+        s => format!("{}d", s / (24 * 60 * 60)),
+    }
+}
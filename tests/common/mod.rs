@@ -0,0 +1,106 @@
+//! Runs the petit binary against a wiremock server standing in for the
+//! Twitter API, with HOME pointed at a throwaway directory.
+
+#![allow(dead_code)]
+
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use std::process::{Output, Stdio};
+use tempfile::TempDir;
+use tokio::process::Command;
+use wiremock::MockServer;
+
+pub const API_KEY: &str = "test-api-key";
+pub const API_SECRET: &str = "test-api-secret";
+
+pub struct Petit {
+    pub server: MockServer,
+    home: TempDir,
+}
+
+impl Petit {
+    pub async fn start() -> Self {
+        Self {
+            server: MockServer::start().await,
+            home: tempfile::tempdir().expect("failed to create a temporary HOME"),
+        }
+    }
+
+    pub fn home(&self) -> &Path {
+        self.home.path()
+    }
+
+    pub fn data_dir(&self) -> PathBuf {
+        self.home().join(".local/share/petit")
+    }
+
+    pub fn cache_dir(&self) -> PathBuf {
+        self.home().join(".cache/petit")
+    }
+
+    pub fn config_dir(&self) -> PathBuf {
+        self.home().join(".config/petit")
+    }
+
+    /// Stores an access token as if `petit login` had been run.
+    pub fn login(&self, name: &str) {
+        let accounts = json!({
+            "active": name,
+            "profiles": {
+                name: { "token": format!("{}-token", name), "secret": format!("{}-secret", name) }
+            }
+        });
+        std::fs::create_dir_all(self.data_dir()).unwrap();
+        std::fs::write(self.data_dir().join("accounts.json"), accounts.to_string()).unwrap();
+    }
+
+    pub fn accounts(&self) -> Value {
+        let text = std::fs::read_to_string(self.data_dir().join("accounts.json")).unwrap();
+        serde_json::from_str(&text).unwrap()
+    }
+
+    pub async fn run(&self, args: &[&str]) -> Output {
+        self.command(args)
+            .output()
+            .await
+            .expect("failed to run petit")
+    }
+
+    pub fn command(&self, args: &[&str]) -> Command {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_petit"));
+        command
+            .args(args)
+            .env("HOME", self.home())
+            .env_remove("XDG_CONFIG_HOME")
+            .env_remove("XDG_CACHE_HOME")
+            .env_remove("XDG_DATA_HOME")
+            .env_remove("PETIT_PASSPHRASE")
+            .env_remove("PETIT_ACCESS_TOKEN")
+            .env_remove("PETIT_ACCESS_TOKEN_SECRET")
+            .env_remove("NO_COLOR")
+            .env_remove("CLICOLOR")
+            .env_remove("CLICOLOR_FORCE")
+            .env("PETIT_API_BASE_URL", self.server.uri())
+            .env("PETIT_API_KEY", API_KEY)
+            .env("PETIT_API_SECRET", API_SECRET)
+            .stdin(Stdio::null());
+        command
+    }
+}
+
+pub fn fixture(name: &str) -> Value {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name);
+    let text = std::fs::read_to_string(&path)
+        .unwrap_or_else(|_| panic!("missing fixture {}", path.display()));
+    serde_json::from_str(&text).unwrap()
+}
+
+pub fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+pub fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
@@ -0,0 +1,2019 @@
+mod common;
+
+use common::{fixture, stderr, stdout, Petit, API_KEY};
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::time::Duration;
+use wiremock::matchers::{method, path, query_param};
+use wiremock::{Mock, Request, ResponseTemplate};
+
+/// Nothing listens on port 1, so connections are refused right away.
+const UNREACHABLE: &str = "http://127.0.0.1:1";
+
+fn ok(name: &str) -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_json(fixture(name))
+}
+
+#[tokio::test]
+async fn tl_signs_requests_with_the_stored_token() {
+    let petit = Petit::start().await;
+    petit.login("alice");
+    Mock::given(method("GET"))
+        .and(path("/1.1/statuses/home_timeline.json"))
+        .and(query_param("count", "30"))
+        .and(|request: &Request| {
+            // wiremock splits header values on commas
+            let authorization = match request.headers.get(&"authorization".into()) {
+                Some(values) => values
+                    .iter()
+                    .map(|x| x.as_str())
+                    .collect::<Vec<_>>()
+                    .join(","),
+                None => return false,
+            };
+            authorization.starts_with("OAuth ")
+                && authorization.contains(&format!("oauth_consumer_key=\"{}\"", API_KEY))
+                && authorization.contains("oauth_token=\"alice-token\"")
+                && authorization.contains("oauth_signature=")
+        })
+        .respond_with(ok("home_timeline.json"))
+        .expect(1)
+        .mount(&petit.server)
+        .await;
+
+    let output = petit.run(&["tl"]).await;
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    let out = stdout(&output);
+    assert!(out.contains("Alice"));
+    assert!(out.contains("RT:@bob"));
+    assert!(out.contains("hello\nworld"));
+}
+
+#[tokio::test]
+async fn tl_json_outputs_the_timeline() {
+    let petit = Petit::start().await;
+    petit.login("alice");
+    Mock::given(method("GET"))
+        .and(path("/1.1/statuses/home_timeline.json"))
+        .respond_with(ok("home_timeline.json"))
+        .mount(&petit.server)
+        .await;
+
+    let output = petit.run(&["tl", "--display", "json"]).await;
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    let json: serde_json::Value = serde_json::from_str(&stdout(&output)).unwrap();
+    let ids = json
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|x| x["id_str"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(ids, ["1368176500000000002", "1368176500000000001"]);
+}
+
+#[tokio::test]
+async fn tl_csv_is_rfc_4180() {
+    let petit = Petit::start().await;
+    petit.login("alice");
+    Mock::given(method("GET"))
+        .and(path("/1.1/statuses/home_timeline.json"))
+        .respond_with(ok("home_timeline.json"))
+        .mount(&petit.server)
+        .await;
+
+    let output = petit.run(&["tl", "--display", "csv"]).await;
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    let out = stdout(&output);
+    assert!(out.starts_with(
+        "id,created_at,user,screen_name,text,retweeted_by,retweet_count,favorite_count,url\r\n"
+    ));
+    let mut reader = csv::Reader::from_reader(out.as_bytes());
+    let rows = reader.records().map(|x| x.unwrap()).collect::<Vec<_>>();
+    assert_eq!(rows.len(), 2);
+    assert!(rows.iter().all(|x| x.len() == 9));
+    assert_eq!(&rows[0][4], "hello\nworld");
+    assert_eq!(&rows[0][5], "bob");
+    assert_eq!(&rows[1][5], "");
+    assert_eq!(
+        &rows[1][8],
+        "https://twitter.com/alice/status/1368176500000000001"
+    );
+}
+
+#[tokio::test]
+async fn tl_tsv_puts_one_tweet_per_line() {
+    let petit = Petit::start().await;
+    petit.login("alice");
+    Mock::given(method("GET"))
+        .and(path("/1.1/statuses/home_timeline.json"))
+        .respond_with(ok("home_timeline.json"))
+        .mount(&petit.server)
+        .await;
+
+    let output = petit.run(&["tl", "--display", "tsv"]).await;
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    let out = stdout(&output);
+    let lines = out.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 3);
+    assert!(lines.iter().all(|x| x.split('\t').count() == 9));
+    assert!(lines[2].starts_with("1368176500000000001\t2021-03-06T"));
+    assert!(lines[2].contains("\tAlice\talice\thello world\t\t"));
+}
+
+#[tokio::test]
+async fn tl_tsv_replaces_tabs_and_line_breaks_in_every_column() {
+    let petit = Petit::start().await;
+    petit.login("alice");
+    let mut tweet = fixture("update.json");
+    tweet["user"]["name"] = "Al\tice\r\nB".into();
+    Mock::given(method("GET"))
+        .and(path("/1.1/statuses/home_timeline.json"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(vec![tweet]))
+        .mount(&petit.server)
+        .await;
+
+    let output = petit.run(&["tl", "--display", "tsv"]).await;
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    let out = stdout(&output);
+    let lines = out.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[1].split('\t').nth(2), Some("Al ice  B"));
+}
+
+#[tokio::test]
+async fn color_follows_the_color_option_and_environment() {
+    let petit = Petit::start().await;
+    petit.login("alice");
+    Mock::given(method("GET"))
+        .and(path("/1.1/statuses/home_timeline.json"))
+        .respond_with(ok("home_timeline.json"))
+        .mount(&petit.server)
+        .await;
+    let colored = |output: &std::process::Output| stdout(output).contains("\u{1b}[");
+
+    let output = petit.run(&["tl"]).await;
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(!colored(&output));
+
+    let output = petit.run(&["tl", "--color", "always"]).await;
+    assert!(colored(&output));
+
+    let output = petit
+        .command(&["tl"])
+        .env("CLICOLOR_FORCE", "1")
+        .output()
+        .await
+        .unwrap();
+    assert!(colored(&output));
+
+    let output = petit
+        .command(&["tl", "--color", "never"])
+        .env("CLICOLOR_FORCE", "1")
+        .output()
+        .await
+        .unwrap();
+    assert!(!colored(&output));
+
+    let output = petit
+        .command(&["tl"])
+        .env("NO_COLOR", "1")
+        .output()
+        .await
+        .unwrap();
+    assert!(!colored(&output));
+
+    let output = petit
+        .command(&["tl"])
+        .env("NO_COLOR", "1")
+        .env("CLICOLOR_FORCE", "1")
+        .output()
+        .await
+        .unwrap();
+    assert!(!colored(&output));
+}
+
+#[tokio::test]
+async fn errors_are_colored_only_when_asked() {
+    let petit = Petit::start().await;
+
+    let output = petit.run(&["tl"]).await;
+    assert!(!output.status.success());
+    assert!(stderr(&output).starts_with("Error: "));
+
+    let output = petit.run(&["tl", "--color", "always"]).await;
+    assert!(!output.status.success());
+    assert!(stderr(&output).starts_with("\u{1b}["));
+}
+
+#[tokio::test]
+async fn tl_passes_since_id() {
+    let petit = Petit::start().await;
+    petit.login("alice");
+    Mock::given(method("GET"))
+        .and(path("/1.1/statuses/home_timeline.json"))
+        .and(query_param("since_id", "1368176500000000001"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("[]"))
+        .expect(1)
+        .mount(&petit.server)
+        .await;
+
+    let output = petit.run(&["tl", "1368176500000000001"]).await;
+
+    assert!(output.status.success(), "{}", stderr(&output));
+}
+
+#[tokio::test]
+async fn tl_ndjson_writes_one_normalized_object_per_line() {
+    let petit = Petit::start().await;
+    petit.login("alice");
+    Mock::given(method("GET"))
+        .and(path("/1.1/statuses/home_timeline.json"))
+        .respond_with(ok("home_timeline.json"))
+        .mount(&petit.server)
+        .await;
+
+    let output = petit.run(&["tl", "--display", "ndjson"]).await;
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    let records = stdout(&output)
+        .lines()
+        .map(|x| serde_json::from_str::<serde_json::Value>(x).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(records.len(), 2);
+    assert!(records.iter().all(|x| x["schema"] == 1));
+    assert_eq!(records[0]["id"], "1368176500000000001");
+    assert_eq!(records[0]["user"]["screen_name"], "alice");
+    assert_eq!(records[0]["retweeted_by"]["screen_name"], "bob");
+    assert_eq!(records[0]["text"], "hello\nworld");
+    assert!(records[0]["created_at"]
+        .as_str()
+        .unwrap()
+        .starts_with("2021-03-06T"));
+    assert_eq!(records[1]["retweeted_by"], serde_json::Value::Null);
+    assert_eq!(records[1]["media"], serde_json::json!([]));
+}
+
+#[tokio::test]
+async fn tl_format_renders_the_template() {
+    let petit = Petit::start().await;
+    petit.login("alice");
+    Mock::given(method("GET"))
+        .and(path("/1.1/statuses/home_timeline.json"))
+        .respond_with(ok("home_timeline.json"))
+        .mount(&petit.server)
+        .await;
+
+    let output = petit
+        .run(&[
+            "tl",
+            "--format",
+            "{created_at:%Y} @{screen_name} {{{retweeted_by}}}\\t{url}",
+        ])
+        .await;
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        "2021 @alice {bob}\thttps://twitter.com/alice/status/1368176500000000001\n\
+         2021 @alice {}\thttps://twitter.com/alice/status/1368176500000000001\n"
+    );
+}
+
+#[tokio::test]
+async fn tl_format_uses_named_templates_from_the_config() {
+    let petit = Petit::start().await;
+    petit.login("alice");
+    std::fs::create_dir_all(petit.config_dir()).unwrap();
+    std::fs::write(
+        petit.config_dir().join("config.toml"),
+        "[templates]\nids = \"{id}\"\n",
+    )
+    .unwrap();
+    Mock::given(method("GET"))
+        .and(path("/1.1/statuses/home_timeline.json"))
+        .respond_with(ok("home_timeline.json"))
+        .mount(&petit.server)
+        .await;
+
+    let output = petit.run(&["tl", "--format", "ids"]).await;
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        "1368176500000000001\n1368176500000000001\n"
+    );
+}
+
+#[tokio::test]
+async fn tl_format_rejects_unknown_fields() {
+    let petit = Petit::start().await;
+    petit.login("alice");
+
+    let output = petit.run(&["tl", "--format", "{nope}"]).await;
+
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("unknown field or style in format: {nope}"));
+}
+
+#[tokio::test]
+async fn tweet_times_follow_the_time_config() {
+    let petit = Petit::start().await;
+    petit.login("alice");
+    std::fs::create_dir_all(petit.config_dir()).unwrap();
+    std::fs::write(
+        petit.config_dir().join("config.toml"),
+        "[time]\ntimezone = \"+09:00\"\nformat = \"%m/%d %H:%M\"\n",
+    )
+    .unwrap();
+    Mock::given(method("GET"))
+        .and(path("/1.1/statuses/home_timeline.json"))
+        .respond_with(ok("home_timeline.json"))
+        .mount(&petit.server)
+        .await;
+
+    let output = petit.run(&["tl"]).await;
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stdout(&output).contains("03/06 20:00"));
+
+    let output = petit
+        .run(&["tl", "--format", "{created_at} {created_at:%H}"])
+        .await;
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stdout(&output).starts_with("03/06 20:00 20\n"));
+
+    let output = petit.run(&["tl", "--display", "csv"]).await;
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stdout(&output).contains(",2021-03-06T20:00:00+09:00,"));
+
+    let output = petit.run(&["tl", "--display", "ndjson"]).await;
+    assert!(stdout(&output).contains("\"created_at\":\"2021-03-06T11:00:00+00:00\""));
+}
+
+#[tokio::test]
+async fn config_set_rejects_unknown_time_zones() {
+    let petit = Petit::start().await;
+
+    let output = petit.run(&["config", "set", "time.timezone", "mars"]).await;
+
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("unknown time zone: mars"));
+    let output = petit.run(&["config", "set", "time.timezone", "utc"]).await;
+    assert!(output.status.success(), "{}", stderr(&output));
+}
+
+#[tokio::test]
+async fn search_prints_results_when_not_on_a_terminal() {
+    let petit = Petit::start().await;
+    petit.login("alice");
+    Mock::given(method("GET"))
+        .and(path("/1.1/search/tweets.json"))
+        .and(query_param("q", "hello"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(serde_json::json!({ "statuses": [fixture("update.json")] })),
+        )
+        .expect(1)
+        .mount(&petit.server)
+        .await;
+
+    let output = petit
+        .run(&["search", "hello", "--format", "{screen_name}: {text}"])
+        .await;
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "alice: good morning\n");
+}
+
+#[tokio::test]
+async fn tl_mentions_and_user_timelines_are_cached_separately() {
+    let petit = Petit::start().await;
+    petit.login("alice");
+    Mock::given(method("GET"))
+        .and(path("/1.1/statuses/mentions_timeline.json"))
+        .respond_with(ok("home_timeline.json"))
+        .expect(1)
+        .mount(&petit.server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/1.1/statuses/user_timeline.json"))
+        .and(query_param("screen_name", "bob"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(vec![fixture("update.json")]))
+        .expect(1)
+        .mount(&petit.server)
+        .await;
+
+    for args in [&["tl", "--mentions"][..], &["tl", "--user", "@bob"][..]] {
+        let output = petit.run(args).await;
+        assert!(output.status.success(), "{}", stderr(&output));
+    }
+    let output = petit
+        .run(&["tl", "--user", "bob", "--format", "{id}"])
+        .await;
+
+    assert_eq!(stdout(&output), "1368176500000000003\n");
+    let cache: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string(petit.cache_dir().join("cache.json")).unwrap(),
+    )
+    .unwrap();
+    let keys = cache["accounts"]["alice"]
+        .as_object()
+        .unwrap()
+        .keys()
+        .cloned()
+        .collect::<Vec<_>>();
+    assert_eq!(keys, ["mentions", "user?screen_name=bob"]);
+}
+
+#[tokio::test]
+async fn tl_list_and_likes_call_their_endpoints() {
+    let petit = Petit::start().await;
+    petit.login("alice");
+    Mock::given(method("GET"))
+        .and(path("/1.1/lists/statuses.json"))
+        .and(query_param("owner_screen_name", "alice"))
+        .and(query_param("slug", "rust"))
+        .and(query_param("include_rts", "true"))
+        .respond_with(ok("home_timeline.json"))
+        .expect(1)
+        .mount(&petit.server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/1.1/lists/statuses.json"))
+        .and(query_param("list_id", "42"))
+        .respond_with(ok("home_timeline.json"))
+        .expect(1)
+        .mount(&petit.server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/1.1/favorites/list.json"))
+        .and(query_param("screen_name", "bob"))
+        .respond_with(ok("home_timeline.json"))
+        .expect(1)
+        .mount(&petit.server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/1.1/favorites/list.json"))
+        .and(|request: &Request| !request.url.query_pairs().any(|(k, _)| k == "screen_name"))
+        .respond_with(ok("home_timeline.json"))
+        .expect(1)
+        .mount(&petit.server)
+        .await;
+
+    for args in [
+        &["tl", "--list", "@alice/rust"][..],
+        &["tl", "--list", "42"][..],
+        &["tl", "--likes", "@bob"][..],
+        &["tl", "--likes"][..],
+    ] {
+        let output = petit.run(args).await;
+        assert!(output.status.success(), "{:?}: {}", args, stderr(&output));
+    }
+
+    let output = petit.run(&["tl", "--list", "rust"]).await;
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("use owner/slug or the list id"));
+}
+
+#[tokio::test]
+async fn muted_tweets_are_hidden_until_unmuted() {
+    let petit = Petit::start().await;
+    petit.login("alice");
+    Mock::given(method("GET"))
+        .and(path("/1.1/statuses/home_timeline.json"))
+        .respond_with(ok("home_timeline.json"))
+        .expect(1)
+        .mount(&petit.server)
+        .await;
+    let tl = ["tl", "--format", "{id} {retweeted_by}"];
+
+    let output = petit.run(&["mute", "add", "retweets", "@Bob"]).await;
+    assert!(output.status.success(), "{}", stderr(&output));
+    let output = petit.run(&tl).await;
+    assert_eq!(stdout(&output), "1368176500000000001 \n");
+
+    let output = petit.run(&["mute", "list"]).await;
+    assert_eq!(stdout(&output), "1\tretweets\tBob\t\n");
+    petit.run(&["mute", "remove", "1"]).await;
+
+    // The cache keeps muted tweets, so unmuting shows them again.
+    let output = petit.run(&tl).await;
+    assert_eq!(
+        stdout(&output),
+        "1368176500000000001 bob\n1368176500000000001 \n"
+    );
+}
+
+#[tokio::test]
+async fn a_broken_mutes_file_only_affects_tweet_listings() {
+    let petit = Petit::start().await;
+    petit.login("alice");
+    Mock::given(method("GET"))
+        .and(path("/1.1/statuses/home_timeline.json"))
+        .respond_with(ok("home_timeline.json"))
+        .mount(&petit.server)
+        .await;
+    std::fs::create_dir_all(petit.config_dir()).unwrap();
+    let mutes = petit.config_dir().join("mutes.toml");
+    std::fs::write(&mutes, "[[rules]]\nkind = \"regex\"\nvalue = \"(\"\n").unwrap();
+
+    let output = petit.run(&["tl", "--display", "tsv"]).await;
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stderr(&output).contains("Skipped the mute regex ("));
+    assert_eq!(stdout(&output).lines().count(), 3);
+    let output = petit.run(&["mute", "remove", "1"]).await;
+    assert!(output.status.success(), "{}", stderr(&output));
+
+    std::fs::write(&mutes, "not toml [").unwrap();
+    let output = petit.run(&["config", "path"]).await;
+    assert!(output.status.success(), "{}", stderr(&output));
+    let output = petit.run(&["tl"]).await;
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("Invalid mute file"));
+}
+
+#[tokio::test]
+async fn mutes_match_words_regexes_and_sources_until_they_expire() {
+    let petit = Petit::start().await;
+    petit.login("alice");
+    Mock::given(method("GET"))
+        .and(path("/1.1/statuses/home_timeline.json"))
+        .respond_with(ok("home_timeline.json"))
+        .mount(&petit.server)
+        .await;
+    let tl = ["tl", "--format", "{retweeted_by}"];
+
+    petit.run(&["mute", "add", "source", "petit"]).await;
+    let output = petit.run(&tl).await;
+    assert_eq!(stdout(&output), "\n");
+
+    petit.run(&["mute", "remove", "1"]).await;
+    std::fs::write(
+        petit.config_dir().join("mutes.toml"),
+        "[[rules]]\nkind = \"word\"\nvalue = \"WORLD\"\nexpires_at = \"2001-01-01T00:00:00Z\"\n",
+    )
+    .unwrap();
+    let output = petit.run(&tl).await;
+    assert_eq!(stdout(&output), "bob\n\n");
+    let output = petit.run(&["mute", "list"]).await;
+    assert!(stdout(&output).ends_with("\texpired\n"));
+
+    // Saving drops the expired rule.
+    petit.run(&["mute", "add", "regex", "^hello\\s"]).await;
+    let output = petit.run(&tl).await;
+    assert_eq!(stdout(&output), "");
+    let output = petit.run(&["mute", "list"]).await;
+    assert_eq!(stdout(&output), "1\tregex\t^hello\\s\t\n");
+
+    let output = petit
+        .run(&["mute", "add", "word", "x", "--until", "2001-01-01"])
+        .await;
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("--until 2001-01-01 is already past"));
+    let output = petit
+        .run(&["mute", "add", "word", "x", "--for", "0s"])
+        .await;
+    assert!(!output.status.success());
+    for long in ["99999999999999999d", "100000000000000d"].iter() {
+        let output = petit
+            .run(&["mute", "add", "word", "x", "--for", long])
+            .await;
+        assert!(!output.status.success());
+        assert!(!stderr(&output).contains("panicked"), "{}", stderr(&output));
+    }
+
+    let output = petit.run(&["mute", "add", "regex", "("]).await;
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("Invalid regex"));
+}
+
+#[tokio::test]
+async fn tl_pages_walks_back_with_max_id() {
+    let petit = Petit::start().await;
+    petit.login("alice");
+    Mock::given(method("GET"))
+        .and(path("/1.1/statuses/home_timeline.json"))
+        .and(query_param("count", "1"))
+        .and(|request: &Request| !request.url.query_pairs().any(|(k, _)| k == "max_id"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(vec![fixture("update.json")]))
+        .expect(1)
+        .mount(&petit.server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/1.1/statuses/home_timeline.json"))
+        .and(query_param("max_id", "1368176500000000002"))
+        .respond_with(ok("home_timeline.json"))
+        .expect(1)
+        .mount(&petit.server)
+        .await;
+
+    let output = petit
+        .run(&["tl", "--count", "1", "--pages", "2", "--display", "json"])
+        .await;
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    let json: serde_json::Value = serde_json::from_str(&stdout(&output)).unwrap();
+    assert_eq!(json.as_array().unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn tl_all_stops_on_an_empty_page() {
+    let petit = Petit::start().await;
+    petit.login("alice");
+    Mock::given(method("GET"))
+        .and(path("/1.1/statuses/home_timeline.json"))
+        .and(query_param("max_id", "1368176500000000009"))
+        .respond_with(ok("home_timeline.json"))
+        .expect(1)
+        .mount(&petit.server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/1.1/statuses/home_timeline.json"))
+        .and(query_param("max_id", "1368176500000000000"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("[]"))
+        .expect(1)
+        .mount(&petit.server)
+        .await;
+
+    let output = petit
+        .run(&[
+            "tl",
+            "--all",
+            "--max-id",
+            "1368176500000000009",
+            "--display",
+            "tsv",
+        ])
+        .await;
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output).lines().count(), 3);
+    assert!(!petit.cache_dir().join("cache.json").exists());
+}
+
+#[tokio::test]
+async fn tl_all_closes_the_output_when_rate_limited() {
+    let petit = Petit::start().await;
+    petit.login("alice");
+    Mock::given(method("GET"))
+        .and(path("/1.1/statuses/home_timeline.json"))
+        .and(query_param("max_id", "1368176500000000009"))
+        .respond_with(ok("home_timeline.json"))
+        .mount(&petit.server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/1.1/statuses/home_timeline.json"))
+        .and(query_param("max_id", "1368176500000000000"))
+        .respond_with(ResponseTemplate::new(429))
+        .mount(&petit.server)
+        .await;
+
+    let output = petit
+        .run(&[
+            "tl",
+            "--all",
+            "--max-id",
+            "1368176500000000009",
+            "--display",
+            "json",
+        ])
+        .await;
+
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("Stopped after 1 page(s)"));
+    let json: serde_json::Value = serde_json::from_str(&stdout(&output)).unwrap();
+    assert_eq!(json.as_array().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn tl_since_and_until_page_through_the_range() {
+    let petit = Petit::start().await;
+    petit.login("alice");
+    Mock::given(method("GET"))
+        .and(path("/1.1/statuses/home_timeline.json"))
+        .and(query_param("since_id", "1367988294251446271"))
+        .and(query_param("max_id", "1368350682117046271"))
+        .respond_with(ok("home_timeline.json"))
+        .expect(1)
+        .mount(&petit.server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/1.1/statuses/home_timeline.json"))
+        .and(query_param("since_id", "1367988294251446271"))
+        .and(query_param("max_id", "1368176500000000000"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("[]"))
+        .expect(1)
+        .mount(&petit.server)
+        .await;
+
+    let output = petit
+        .run(&[
+            "tl",
+            "--since",
+            "2021-03-06T00:00:00Z",
+            "--until",
+            "2021-03-07T00:00:00Z",
+            "--display",
+            "tsv",
+        ])
+        .await;
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output).lines().count(), 3);
+}
+
+#[tokio::test]
+async fn search_since_pages_recent_results() {
+    let petit = Petit::start().await;
+    petit.login("alice");
+    Mock::given(method("GET"))
+        .and(path("/1.1/search/tweets.json"))
+        .and(query_param("q", "hello"))
+        .and(query_param("result_type", "recent"))
+        .and(query_param("since_id", "1367988294251446271"))
+        .and(|request: &Request| !request.url.query_pairs().any(|(k, _)| k == "max_id"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(serde_json::json!({ "statuses": [fixture("update.json")] })),
+        )
+        .expect(1)
+        .mount(&petit.server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/1.1/search/tweets.json"))
+        .and(query_param("max_id", "1368176500000000002"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(serde_json::json!({ "statuses": [] })),
+        )
+        .expect(1)
+        .mount(&petit.server)
+        .await;
+
+    let output = petit
+        .run(&[
+            "search",
+            "hello",
+            "--since",
+            "2021-03-06T00:00:00Z",
+            "--format",
+            "{screen_name}: {text}",
+        ])
+        .await;
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "alice: good morning\n");
+    assert!(stderr(&output).contains("only returns tweets of the last 7 days"));
+}
+
+#[tokio::test]
+async fn offline_tl_range_serves_the_cached_tweets_in_it() {
+    let petit = Petit::start().await;
+    petit.login("alice");
+    Mock::given(method("GET"))
+        .and(path("/1.1/statuses/home_timeline.json"))
+        .respond_with(ok("home_timeline.json"))
+        .mount(&petit.server)
+        .await;
+    let output = petit.run(&["tl"]).await;
+    assert!(output.status.success(), "{}", stderr(&output));
+
+    let output = petit
+        .run(&[
+            "--offline",
+            "tl",
+            "--since",
+            "2021-03-06T00:00:00Z",
+            "--max-id",
+            "1368176500000000001",
+            "--format",
+            "{id}",
+        ])
+        .await;
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "1368176500000000001\n");
+    assert!(stderr(&output).contains("Offline"));
+}
+
+#[tokio::test]
+async fn offline_user_range_serves_the_archived_tweets_they_posted() {
+    let petit = Petit::start().await;
+    petit.login("alice");
+    Mock::given(method("GET"))
+        .and(path("/1.1/statuses/home_timeline.json"))
+        .respond_with(ok("home_timeline.json"))
+        .mount(&petit.server)
+        .await;
+    let output = petit.run(&["tl"]).await;
+    assert!(output.status.success(), "{}", stderr(&output));
+
+    // bob's retweet of alice's tweet is bob's, even though alice's tweet is shown.
+    let output = petit
+        .run(&[
+            "--offline",
+            "tl",
+            "--user",
+            "@alice",
+            "--since",
+            "2021-03-06T00:00:00Z",
+            "--format",
+            "{id}",
+        ])
+        .await;
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "1368176500000000001\n");
+}
+
+#[tokio::test]
+async fn since_rejects_what_is_neither_a_time_nor_a_duration() {
+    let petit = Petit::start().await;
+    petit.login("alice");
+
+    let output = petit.run(&["tl", "--since", "yesterday"]).await;
+
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("invalid time: yesterday"));
+}
+
+#[tokio::test]
+async fn tl_follow_prints_new_tweets_oldest_first_until_interrupted() {
+    let petit = Petit::start().await;
+    petit.login("alice");
+    Mock::given(method("GET"))
+        .and(path("/1.1/statuses/home_timeline.json"))
+        .and(query_param("since_id", "1368176500000000002"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(vec![fixture("update.json")]))
+        .mount(&petit.server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/1.1/statuses/home_timeline.json"))
+        .and(query_param("since_id", "1368176500000000001"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+            fixture("update.json"),
+            fixture("home_timeline.json")[0]
+        ])))
+        .mount(&petit.server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/1.1/statuses/home_timeline.json"))
+        .respond_with(ok("home_timeline.json"))
+        .mount(&petit.server)
+        .await;
+
+    let child = petit
+        .command(&["tl", "--follow", "--interval", "1s", "--display", "json"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    for _ in 0..100 {
+        if petit.server.received_requests().await.unwrap().len() >= 3 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    let pid = child.id().unwrap().to_string();
+    std::process::Command::new("kill")
+        .args(["-INT", &pid])
+        .status()
+        .unwrap();
+    let output = child.wait_with_output().await.unwrap();
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    let json: serde_json::Value = serde_json::from_str(&stdout(&output)).unwrap();
+    let ids = json
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|x| x["id_str"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        ids,
+        [
+            "1368176500000000001",
+            "1368176500000000002",
+            "1368176500000000003"
+        ]
+    );
+}
+
+#[tokio::test]
+async fn tl_follow_exits_quietly_when_stdout_is_closed() {
+    let petit = Petit::start().await;
+    petit.login("alice");
+    Mock::given(method("GET"))
+        .and(path("/1.1/statuses/home_timeline.json"))
+        .respond_with(ok("home_timeline.json"))
+        .mount(&petit.server)
+        .await;
+
+    let mut child = petit
+        .command(&["tl", "--follow"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    drop(child.stdout.take());
+    let output = tokio::time::timeout(Duration::from_secs(10), child.wait_with_output())
+        .await
+        .expect("petit kept running")
+        .unwrap();
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stderr(&output), "");
+}
+
+#[tokio::test]
+async fn tl_follow_exits_when_stdout_is_closed_on_a_quiet_timeline() {
+    let petit = Petit::start().await;
+    petit.login("alice");
+    Mock::given(method("GET"))
+        .and(path("/1.1/statuses/home_timeline.json"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("[]"))
+        .mount(&petit.server)
+        .await;
+
+    let mut child = petit
+        .command(&["tl", "--follow", "--interval", "1h"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    drop(child.stdout.take());
+    let output = tokio::time::timeout(Duration::from_secs(10), child.wait_with_output())
+        .await
+        .expect("petit kept running")
+        .unwrap();
+
+    assert!(output.status.success(), "{}", stderr(&output));
+}
+
+#[tokio::test]
+async fn tl_follow_starts_after_the_cached_tweets() {
+    let petit = Petit::start().await;
+    petit.login("alice");
+    Mock::given(method("GET"))
+        .and(path("/1.1/statuses/home_timeline.json"))
+        .and(query_param("since_id", "1368176500000000001"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+            fixture("update.json"),
+            fixture("home_timeline.json")[0]
+        ])))
+        .mount(&petit.server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/1.1/statuses/home_timeline.json"))
+        .and(query_param("since_id", "1368176500000000002"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(vec![fixture("update.json")]))
+        .mount(&petit.server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/1.1/statuses/home_timeline.json"))
+        .respond_with(ok("home_timeline.json"))
+        .mount(&petit.server)
+        .await;
+    let output = petit.run(&["tl"]).await;
+    assert!(output.status.success(), "{}", stderr(&output));
+
+    let child = petit
+        .command(&["tl", "--follow", "--interval", "1s", "--format", "{id}"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    for _ in 0..100 {
+        if petit.server.received_requests().await.unwrap().len() >= 3 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    let pid = child.id().unwrap().to_string();
+    std::process::Command::new("kill")
+        .args(["-INT", &pid])
+        .status()
+        .unwrap();
+    let output = child.wait_with_output().await.unwrap();
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "1368176500000000003\n");
+}
+
+#[tokio::test]
+async fn rate_limit_reports_when_to_retry() {
+    let petit = Petit::start().await;
+    petit.login("alice");
+    Mock::given(method("GET"))
+        .and(path("/1.1/statuses/home_timeline.json"))
+        .respond_with(
+            ResponseTemplate::new(429)
+                .insert_header("x-rate-limit-reset", "1615032000")
+                .set_body_string(r#"{"errors":[{"code":88,"message":"Rate limit exceeded"}]}"#),
+        )
+        .mount(&petit.server)
+        .await;
+
+    let output = petit.run(&["tl"]).await;
+
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("Rate limit exceeded. try again after"));
+}
+
+#[tokio::test]
+async fn tl_is_served_from_the_cache_within_the_ttl() {
+    let petit = Petit::start().await;
+    petit.login("alice");
+    Mock::given(method("GET"))
+        .and(path("/1.1/statuses/home_timeline.json"))
+        .respond_with(ok("home_timeline.json"))
+        .expect(1)
+        .mount(&petit.server)
+        .await;
+
+    let first = petit.run(&["tl", "--display", "json"]).await;
+    let second = petit.run(&["tl", "--display", "json"]).await;
+
+    assert!(second.status.success(), "{}", stderr(&second));
+    assert_eq!(stdout(&first), stdout(&second));
+    assert!(petit.cache_dir().join("cache.json").is_file());
+}
+
+#[tokio::test]
+async fn tl_without_login_asks_to_login() {
+    let petit = Petit::start().await;
+
+    let output = petit.run(&["tl"]).await;
+
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("petit login"));
+}
+
+#[tokio::test]
+async fn tweet_posts_the_status() {
+    let petit = Petit::start().await;
+    petit.login("alice");
+    Mock::given(method("POST"))
+        .and(path("/1.1/statuses/update.json"))
+        .and(query_param("status", "good morning"))
+        .respond_with(ok("update.json"))
+        .expect(1)
+        .mount(&petit.server)
+        .await;
+
+    let output = petit.run(&["tweet", "good morning"]).await;
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "success!");
+}
+
+#[tokio::test]
+async fn whoami_json_reports_the_user() {
+    let petit = Petit::start().await;
+    petit.login("work");
+    Mock::given(method("GET"))
+        .and(path("/1.1/account/verify_credentials.json"))
+        .respond_with(ok("verify_credentials.json"))
+        .mount(&petit.server)
+        .await;
+
+    let output = petit.run(&["whoami", "--display", "json"]).await;
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    let json: serde_json::Value = serde_json::from_str(&stdout(&output)).unwrap();
+    assert_eq!(json["account"], "work");
+    assert_eq!(json["screen_name"], "alice");
+    assert_eq!(json["followers_count"], 10);
+}
+
+#[tokio::test]
+async fn rejected_token_is_reported() {
+    let petit = Petit::start().await;
+    petit.login("alice");
+    Mock::given(method("GET"))
+        .and(path("/1.1/account/verify_credentials.json"))
+        .respond_with(ResponseTemplate::new(401).set_body_json(fixture("invalid_token.json")))
+        .mount(&petit.server)
+        .await;
+
+    let output = petit.run(&["whoami"]).await;
+
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("The access token was rejected"));
+}
+
+#[tokio::test]
+async fn login_with_token_stores_the_profile() {
+    let petit = Petit::start().await;
+    Mock::given(method("GET"))
+        .and(path("/1.1/account/verify_credentials.json"))
+        .respond_with(ok("verify_credentials.json"))
+        .expect(1)
+        .mount(&petit.server)
+        .await;
+
+    let output = petit
+        .run(&[
+            "login",
+            "--token",
+            "imported",
+            "--secret",
+            "imported-secret",
+        ])
+        .await;
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    let accounts = petit.accounts();
+    assert_eq!(accounts["active"], "alice");
+    assert_eq!(accounts["profiles"]["alice"]["token"], "imported");
+}
+
+#[tokio::test]
+async fn login_creates_the_profile_named_by_account() {
+    let petit = Petit::start().await;
+    petit.login("alice");
+    Mock::given(method("GET"))
+        .and(path("/1.1/account/verify_credentials.json"))
+        .respond_with(ok("verify_credentials.json"))
+        .mount(&petit.server)
+        .await;
+
+    let output = petit
+        .run(&[
+            "--account",
+            "work",
+            "login",
+            "--token",
+            "imported",
+            "--secret",
+            "imported-secret",
+        ])
+        .await;
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    let accounts = petit.accounts();
+    assert_eq!(accounts["active"], "work");
+    assert_eq!(accounts["profiles"]["work"]["token"], "imported");
+    assert_eq!(accounts["profiles"]["alice"]["token"], "alice-token");
+
+    let output = petit.run(&["--account", "nobody", "tl"]).await;
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("No such account: nobody"));
+}
+
+#[tokio::test]
+async fn pin_login_goes_through_the_base_url() {
+    use tokio::io::AsyncWriteExt;
+
+    let petit = Petit::start().await;
+    Mock::given(method("POST"))
+        .and(path("/oauth/request_token"))
+        .and(|request: &Request| {
+            request
+                .headers
+                .get(&"authorization".into())
+                .map(|x| {
+                    x.iter()
+                        .any(|x| x.as_str().contains("oauth_callback=\"oob\""))
+                })
+                .unwrap_or(false)
+        })
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            "oauth_token=req&oauth_token_secret=req-secret&oauth_callback_confirmed=true",
+        ))
+        .expect(1)
+        .mount(&petit.server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/oauth/access_token"))
+        .and(|request: &Request| {
+            let header = request
+                .headers
+                .get(&"authorization".into())
+                .map(|x| x.iter().map(|x| x.as_str()).collect::<Vec<_>>().join(","))
+                .unwrap_or_default();
+            header.contains("oauth_token=\"req\"") && header.contains("oauth_verifier=\"1234\"")
+        })
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_string("oauth_token=alice-token&oauth_token_secret=alice-secret"),
+        )
+        .expect(1)
+        .mount(&petit.server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/1.1/account/verify_credentials.json"))
+        .respond_with(ok("verify_credentials.json"))
+        .expect(1)
+        .mount(&petit.server)
+        .await;
+
+    let mut child = petit
+        .command(&["login"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"1234\n")
+        .await
+        .unwrap();
+    let output = child.wait_with_output().await.unwrap();
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stdout(&output).contains(&format!(
+        "{}/oauth/authorize?oauth_token=req",
+        petit.server.uri()
+    )));
+    assert_eq!(
+        petit.accounts()["profiles"]["alice"]["token"],
+        "alice-token"
+    );
+}
+
+#[tokio::test]
+async fn callback_login_only_accepts_its_own_request_token() {
+    use tokio::io::{AsyncBufReadExt, BufReader};
+
+    let petit = Petit::start().await;
+    Mock::given(method("POST"))
+        .and(path("/oauth/request_token"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_string("oauth_token=req&oauth_token_secret=s"),
+        )
+        .mount(&petit.server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/oauth/access_token"))
+        .and(|request: &Request| {
+            // The verifier arrives percent-encoded and is encoded once more in the header.
+            request
+                .headers
+                .get(&"authorization".into())
+                .map(|x| {
+                    x.iter()
+                        .any(|x| x.as_str().contains("oauth_verifier=\"a%2Bb\""))
+                })
+                .unwrap_or(false)
+        })
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_string("oauth_token=alice-token&oauth_token_secret=alice-secret"),
+        )
+        .expect(1)
+        .mount(&petit.server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/1.1/account/verify_credentials.json"))
+        .respond_with(ok("verify_credentials.json"))
+        .mount(&petit.server)
+        .await;
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+
+    let mut child = petit
+        .command(&["login", "--callback", "--port", &port.to_string()])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut lines = BufReader::new(child.stdout.take().unwrap()).lines();
+    while let Some(line) = lines.next_line().await.unwrap() {
+        if line.starts_with("Waiting for the callback") {
+            break;
+        }
+    }
+    let url = |target: &str| format!("http://127.0.0.1:{}{}", port, target);
+
+    let response = reqwest::get(&url("/other?oauth_token=req&oauth_verifier=evil"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 404);
+    let response = reqwest::get(&url("/callback?oauth_token=forged&oauth_verifier=evil"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 400);
+    let response = reqwest::get(&url("/callback?oauth_token=req&oauth_verifier=a%2Bb"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+
+    let output = child.wait_with_output().await.unwrap();
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        petit.accounts()["profiles"]["alice"]["token"],
+        "alice-token"
+    );
+}
+
+#[tokio::test]
+async fn login_replaces_the_profile_named_by_account() {
+    let petit = Petit::start().await;
+    petit.login("work");
+    Mock::given(method("GET"))
+        .and(path("/1.1/account/verify_credentials.json"))
+        .respond_with(ok("verify_credentials.json"))
+        .mount(&petit.server)
+        .await;
+
+    let output = petit
+        .run(&[
+            "--account",
+            "work",
+            "login",
+            "--token",
+            "imported",
+            "--secret",
+            "imported-secret",
+        ])
+        .await;
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    let accounts = petit.accounts();
+    assert_eq!(accounts["profiles"]["work"]["token"], "imported");
+    assert!(accounts["profiles"].get("alice").is_none());
+}
+
+#[tokio::test]
+async fn removing_the_active_account_tells_which_one_is_active_now() {
+    let petit = Petit::start().await;
+    let accounts = serde_json::json!({
+        "active": "alice",
+        "profiles": {
+            "alice": { "token": "alice-token", "secret": "alice-secret" },
+            "work": { "token": "work-token", "secret": "work-secret" }
+        }
+    });
+    std::fs::create_dir_all(petit.data_dir()).unwrap();
+    std::fs::write(petit.data_dir().join("accounts.json"), accounts.to_string()).unwrap();
+
+    let output = petit.run(&["account", "remove", "alice"]).await;
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "Removed alice\nSwitched to work\n");
+    assert_eq!(petit.accounts()["active"], "work");
+}
+
+#[tokio::test]
+async fn logout_revokes_and_forgets_the_token() {
+    let petit = Petit::start().await;
+    petit.login("alice");
+    Mock::given(method("POST"))
+        .and(path("/1.1/oauth/invalidate_token"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_string(r#"{"access_token":"ACCESS_TOKEN"}"#),
+        )
+        .expect(1)
+        .mount(&petit.server)
+        .await;
+
+    let output = petit.run(&["logout"]).await;
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(petit.accounts()["profiles"].as_object().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn base_url_can_be_set_in_the_config() {
+    let petit = Petit::start().await;
+    petit.login("alice");
+    std::fs::create_dir_all(petit.config_dir()).unwrap();
+    std::fs::write(
+        petit.config_dir().join("config.toml"),
+        format!("[api]\nbase_url = \"{}\"\n", petit.server.uri()),
+    )
+    .unwrap();
+    Mock::given(method("POST"))
+        .and(path("/1.1/statuses/update.json"))
+        .respond_with(ok("update.json"))
+        .expect(1)
+        .mount(&petit.server)
+        .await;
+
+    let output = petit
+        .command(&["tweet", "hi"])
+        .env_remove("PETIT_API_BASE_URL")
+        .output()
+        .await
+        .unwrap();
+
+    assert!(output.status.success(), "{}", stderr(&output));
+}
+
+#[tokio::test]
+async fn account_switch_changes_the_active_profile() {
+    let petit = Petit::start().await;
+    petit.login("alice");
+    let mut accounts = petit.accounts();
+    accounts["profiles"]["bob"] =
+        serde_json::json!({ "token": "bob-token", "secret": "bob-secret" });
+    std::fs::write(petit.data_dir().join("accounts.json"), accounts.to_string()).unwrap();
+
+    let output = petit.run(&["account", "switch", "bob"]).await;
+    assert!(output.status.success(), "{}", stderr(&output));
+    let output = petit.run(&["account", "list"]).await;
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "  alice\n* bob\n");
+}
+
+#[tokio::test]
+async fn switching_accounts_keeps_the_cache_of_each() {
+    let petit = Petit::start().await;
+    petit.login("alice");
+    let mut accounts = petit.accounts();
+    accounts["profiles"]["bob"] =
+        serde_json::json!({ "token": "bob-token", "secret": "bob-secret" });
+    std::fs::write(petit.data_dir().join("accounts.json"), accounts.to_string()).unwrap();
+    Mock::given(method("GET"))
+        .and(path("/1.1/statuses/home_timeline.json"))
+        .respond_with(ok("home_timeline.json"))
+        .expect(2)
+        .mount(&petit.server)
+        .await;
+
+    for account in ["alice", "bob", "alice", "bob"] {
+        let output = petit.run(&["--account", account, "tl"]).await;
+        assert!(output.status.success(), "{}", stderr(&output));
+    }
+
+    let text = std::fs::read_to_string(petit.cache_dir().join("cache.json")).unwrap();
+    let cache: serde_json::Value = serde_json::from_str(&text).unwrap();
+    assert_eq!(cache["accounts"]["alice"]["home"]["misses"], 1);
+    assert_eq!(cache["accounts"]["bob"]["home"]["misses"], 1);
+}
+
+#[tokio::test]
+async fn cache_stats_and_clear_are_scoped_to_the_current_account() {
+    let petit = Petit::start().await;
+    petit.login("alice");
+    let mut accounts = petit.accounts();
+    accounts["profiles"]["bob"] =
+        serde_json::json!({ "token": "bob-token", "secret": "bob-secret" });
+    std::fs::write(petit.data_dir().join("accounts.json"), accounts.to_string()).unwrap();
+    Mock::given(method("GET"))
+        .and(path("/1.1/statuses/home_timeline.json"))
+        .respond_with(ok("home_timeline.json"))
+        .mount(&petit.server)
+        .await;
+    for account in ["alice", "bob"] {
+        let output = petit.run(&["--account", account, "tl"]).await;
+        assert!(output.status.success(), "{}", stderr(&output));
+    }
+
+    let output = petit.run(&["cache", "stats"]).await;
+    assert!(stdout(&output).starts_with("account: alice\n"));
+    assert!(!stdout(&output).contains("bob"));
+    let output = petit.run(&["cache", "stats", "--all"]).await;
+    assert!(stdout(&output).contains("account: alice\n"));
+    assert!(stdout(&output).contains("account: bob\n"));
+
+    let output = petit.run(&["cache", "clear"]).await;
+    assert!(output.status.success(), "{}", stderr(&output));
+    let output = petit.run(&["cache", "stats", "--all"]).await;
+    assert!(!stdout(&output).contains("account: alice\n"));
+    assert!(stdout(&output).contains("account: bob\n"));
+
+    let output = petit.run(&["cache", "clear", "--all"]).await;
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(!petit.cache_dir().join("cache.json").exists());
+}
+
+#[tokio::test]
+async fn config_set_is_read_back_by_get() {
+    let petit = Petit::start().await;
+
+    let output = petit.run(&["config", "set", "timeline.count", "5"]).await;
+    assert!(output.status.success(), "{}", stderr(&output));
+    let output = petit.run(&["config", "get", "timeline.count"]).await;
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output).trim(), "5");
+    let output = petit.run(&["config", "path"]).await;
+    assert_eq!(
+        stdout(&output).trim(),
+        petit.config_dir().join("config.toml").display().to_string()
+    );
+}
+
+#[tokio::test]
+async fn config_set_keeps_numeric_text_as_text_where_text_is_expected() {
+    let petit = Petit::start().await;
+
+    let output = petit.run(&["config", "set", "api.key", "1234567890"]).await;
+    assert!(output.status.success(), "{}", stderr(&output));
+    let output = petit
+        .run(&["config", "set", "cache.endpoint_ttl.search", "60"])
+        .await;
+    assert!(output.status.success(), "{}", stderr(&output));
+
+    let text = std::fs::read_to_string(petit.config_dir().join("config.toml")).unwrap();
+    assert!(text.contains("key = '1234567890'"), "{}", text);
+    assert!(text.contains("search = 60"), "{}", text);
+}
+
+#[tokio::test]
+async fn tl_replaces_the_cache_when_the_cached_tweets_are_not_reached() {
+    let petit = Petit::start().await;
+    petit.login("alice");
+    std::fs::create_dir_all(petit.config_dir()).unwrap();
+    std::fs::write(petit.config_dir().join("config.toml"), "[cache]\nttl = 0\n").unwrap();
+    Mock::given(method("GET"))
+        .and(path("/1.1/statuses/home_timeline.json"))
+        .respond_with(ok("home_timeline.json"))
+        .up_to_n_times(1)
+        .mount(&petit.server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/1.1/statuses/home_timeline.json"))
+        .and(query_param("since_id", "1368176500000000001"))
+        .and(|request: &Request| !request.url.query_pairs().any(|(k, _)| k == "max_id"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(vec![fixture("update.json")]))
+        .expect(1)
+        .mount(&petit.server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/1.1/statuses/home_timeline.json"))
+        .and(query_param("since_id", "1368176500000000001"))
+        .and(query_param("max_id", "1368176500000000002"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("[]"))
+        .expect(1)
+        .mount(&petit.server)
+        .await;
+
+    petit.run(&["tl"]).await;
+    let output = petit.run(&["tl", "--format", "{id}"]).await;
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "1368176500000000003\n");
+}
+
+#[tokio::test]
+async fn tl_merges_new_tweets_into_the_cache() {
+    let petit = Petit::start().await;
+    petit.login("alice");
+    std::fs::create_dir_all(petit.config_dir()).unwrap();
+    std::fs::write(petit.config_dir().join("config.toml"), "[cache]\nttl = 0\n").unwrap();
+    Mock::given(method("GET"))
+        .and(path("/1.1/statuses/home_timeline.json"))
+        .respond_with(ok("home_timeline.json"))
+        .up_to_n_times(1)
+        .mount(&petit.server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/1.1/statuses/home_timeline.json"))
+        .and(query_param("since_id", "1368176500000000001"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+            fixture("update.json"),
+            fixture("home_timeline.json")[0]
+        ])))
+        .expect(1)
+        .mount(&petit.server)
+        .await;
+
+    petit.run(&["tl"]).await;
+    let output = petit.run(&["tl", "--display", "json"]).await;
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    let json: serde_json::Value = serde_json::from_str(&stdout(&output)).unwrap();
+    let ids = json
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|x| x["id_str"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        ids,
+        [
+            "1368176500000000003",
+            "1368176500000000002",
+            "1368176500000000001"
+        ]
+    );
+}
+
+#[tokio::test]
+async fn archive_search_finds_fetched_tweets_offline() {
+    let petit = Petit::start().await;
+    petit.login("alice");
+    Mock::given(method("GET"))
+        .and(path("/1.1/statuses/home_timeline.json"))
+        .respond_with(ok("home_timeline.json"))
+        .expect(1)
+        .mount(&petit.server)
+        .await;
+
+    petit.run(&["tl"]).await;
+    let output = petit
+        .run(&["archive", "search", "WORLD hel", "--display", "tsv"])
+        .await;
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    let out = stdout(&output);
+    let ids = out
+        .lines()
+        .skip(1)
+        .map(|x| x.split('\t').next().unwrap())
+        .collect::<Vec<_>>();
+    // The retweet is shown as the original tweet
+    assert_eq!(ids, ["1368176500000000001", "1368176500000000001"]);
+
+    let output = petit.run(&["archive", "search", "nothing"]).await;
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "");
+
+    // Words shorter than a trigram still match.
+    let output = petit
+        .run(&["archive", "search", "wo ld", "--format", "{id}"])
+        .await;
+    assert_eq!(
+        stdout(&output),
+        "1368176500000000001\n1368176500000000001\n"
+    );
+    let output = petit
+        .run(&["archive", "search", "xy", "--format", "{id}"])
+        .await;
+    assert_eq!(stdout(&output), "");
+}
+
+#[tokio::test]
+async fn tl_works_when_the_archive_cannot_be_written() {
+    let petit = Petit::start().await;
+    petit.login("alice");
+    Mock::given(method("GET"))
+        .and(path("/1.1/statuses/home_timeline.json"))
+        .respond_with(ok("home_timeline.json"))
+        .expect(1)
+        .mount(&petit.server)
+        .await;
+    // A directory in place of the database cannot be opened.
+    std::fs::create_dir_all(petit.data_dir().join("archive.db")).unwrap();
+
+    let output = petit.run(&["tl", "--display", "tsv"]).await;
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stderr(&output).contains("Failed to archive tweets"));
+    assert_eq!(stdout(&output).lines().count(), 3);
+    let output = petit.run(&["--offline", "tl", "--display", "tsv"]).await;
+    assert_eq!(stdout(&output).lines().count(), 3);
+}
+
+#[tokio::test]
+async fn cache_stats_counts_hits_and_misses() {
+    let petit = Petit::start().await;
+    petit.login("alice");
+    Mock::given(method("GET"))
+        .and(path("/1.1/statuses/home_timeline.json"))
+        .respond_with(ok("home_timeline.json"))
+        .expect(1)
+        .mount(&petit.server)
+        .await;
+
+    petit.run(&["tl"]).await;
+    let written = std::fs::read_to_string(petit.cache_dir().join("cache.json")).unwrap();
+    petit.run(&["tl"]).await;
+    // A hit does not rewrite the cache.
+    assert_eq!(
+        std::fs::read_to_string(petit.cache_dir().join("cache.json")).unwrap(),
+        written
+    );
+    let output = petit.run(&["cache", "stats"]).await;
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    let out = stdout(&output);
+    let home = out.lines().find(|x| x.starts_with("home ")).unwrap();
+    assert_eq!(
+        home.split_whitespace().collect::<Vec<_>>(),
+        ["home", "2", "0s", "1", "1", "50%"]
+    );
+
+    let output = petit.run(&["cache", "clear", "home"]).await;
+    assert!(output.status.success(), "{}", stderr(&output));
+    let output = petit.run(&["cache", "stats"]).await;
+    assert!(!stdout(&output).contains("home "));
+}
+
+#[tokio::test]
+async fn legacy_cache_is_served_as_the_home_timeline() {
+    let petit = Petit::start().await;
+    std::fs::write(
+        petit.home().join(".petit"),
+        r#"{"token":"old-token","secret":"old-secret"}"#,
+    )
+    .unwrap();
+    let legacy = serde_json::json!({
+        "latest_call": chrono::Utc::now(),
+        "timeline": fixture("home_timeline.json"),
+        "count": 0,
+    });
+    std::fs::create_dir_all(petit.home().join(".cache")).unwrap();
+    std::fs::write(
+        petit.home().join(".cache").join("petit"),
+        legacy.to_string(),
+    )
+    .unwrap();
+
+    let output = petit.run(&["tl", "--display", "tsv"]).await;
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output).lines().count(), 3);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn legacy_token_is_moved_into_a_private_data_dir() {
+    use std::os::unix::fs::PermissionsExt;
+    let petit = Petit::start().await;
+    let legacy = petit.home().join(".petit");
+    std::fs::write(&legacy, r#"{"token":"old-token","secret":"old-secret"}"#).unwrap();
+    std::fs::set_permissions(&legacy, std::fs::Permissions::from_mode(0o644)).unwrap();
+
+    let output = petit.run(&["account", "list"]).await;
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(!legacy.exists());
+    let mode = |path: PathBuf| std::fs::metadata(path).unwrap().permissions().mode() & 0o777;
+    assert_eq!(mode(petit.data_dir()), 0o700);
+    assert_eq!(mode(petit.data_dir().join("accounts.json")), 0o600);
+}
+
+#[tokio::test]
+async fn concurrent_runs_leave_a_valid_cache() {
+    let petit = Petit::start().await;
+    petit.login("alice");
+    std::fs::create_dir_all(petit.config_dir()).unwrap();
+    std::fs::write(petit.config_dir().join("config.toml"), "[cache]\nttl = 0\n").unwrap();
+    Mock::given(method("GET"))
+        .and(path("/1.1/statuses/home_timeline.json"))
+        .respond_with(ok("home_timeline.json"))
+        .mount(&petit.server)
+        .await;
+
+    let runs = (0..8).map(|_| petit.run(&["tl"])).collect::<Vec<_>>();
+    for output in futures::future::join_all(runs).await {
+        assert!(output.status.success(), "{}", stderr(&output));
+    }
+
+    let text = std::fs::read_to_string(petit.cache_dir().join("cache.json")).unwrap();
+    let cache: serde_json::Value = serde_json::from_str(&text).unwrap();
+    assert_eq!(cache["version"], 2);
+    assert_eq!(cache["accounts"]["alice"]["home"]["misses"], 8);
+}
+
+#[tokio::test]
+async fn cache_from_a_newer_version_is_not_overwritten() {
+    let petit = Petit::start().await;
+    petit.login("alice");
+    let newer = r#"{"version":99,"account":"alice","shards":[]}"#;
+    std::fs::create_dir_all(petit.cache_dir()).unwrap();
+    std::fs::write(petit.cache_dir().join("cache.json"), newer).unwrap();
+    Mock::given(method("GET"))
+        .and(path("/1.1/statuses/home_timeline.json"))
+        .respond_with(ok("home_timeline.json"))
+        .mount(&petit.server)
+        .await;
+
+    let output = petit.run(&["tl"]).await;
+
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("newer petit"));
+    let text = std::fs::read_to_string(petit.cache_dir().join("cache.json")).unwrap();
+    assert_eq!(text, newer);
+}
+
+#[tokio::test]
+async fn truncated_cache_is_moved_aside() {
+    let petit = Petit::start().await;
+    petit.login("alice");
+    std::fs::create_dir_all(petit.cache_dir()).unwrap();
+    std::fs::write(petit.cache_dir().join("cache.json"), r#"{"version":1,"acc"#).unwrap();
+    Mock::given(method("GET"))
+        .and(path("/1.1/statuses/home_timeline.json"))
+        .respond_with(ok("home_timeline.json"))
+        .mount(&petit.server)
+        .await;
+
+    let output = petit.run(&["tl"]).await;
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(petit.cache_dir().join("cache.json.broken").is_file());
+}
+
+#[tokio::test]
+async fn truncated_cache_is_left_in_place_by_readers() {
+    let petit = Petit::start().await;
+    petit.login("alice");
+    std::fs::create_dir_all(petit.cache_dir()).unwrap();
+    let truncated = r#"{"version":1,"acc"#;
+    std::fs::write(petit.cache_dir().join("cache.json"), truncated).unwrap();
+
+    let output = petit.run(&["cache", "stats"]).await;
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(!petit.cache_dir().join("cache.json.broken").exists());
+    let text = std::fs::read_to_string(petit.cache_dir().join("cache.json")).unwrap();
+    assert_eq!(text, truncated);
+}
+
+#[tokio::test]
+async fn offline_tl_serves_the_cache_marked_as_stale() {
+    let petit = Petit::start().await;
+    petit.login("alice");
+    Mock::given(method("GET"))
+        .and(path("/1.1/statuses/home_timeline.json"))
+        .respond_with(ok("home_timeline.json"))
+        .expect(1)
+        .mount(&petit.server)
+        .await;
+    std::fs::create_dir_all(petit.config_dir()).unwrap();
+    std::fs::write(petit.config_dir().join("config.toml"), "[cache]\nttl = 0\n").unwrap();
+
+    petit.run(&["tl"]).await;
+    let output = petit.run(&["--offline", "tl", "--display", "tsv"]).await;
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output).lines().count(), 3);
+    assert!(stderr(&output).contains("Offline: showing data fetched at"));
+}
+
+#[tokio::test]
+async fn tl_falls_back_to_the_cache_when_the_network_is_down() {
+    let petit = Petit::start().await;
+    petit.login("alice");
+    Mock::given(method("GET"))
+        .and(path("/1.1/statuses/home_timeline.json"))
+        .respond_with(ok("home_timeline.json"))
+        .mount(&petit.server)
+        .await;
+    std::fs::create_dir_all(petit.config_dir()).unwrap();
+    std::fs::write(petit.config_dir().join("config.toml"), "[cache]\nttl = 0\n").unwrap();
+    petit.run(&["tl"]).await;
+
+    let output = petit
+        .command(&["tl", "--display", "tsv"])
+        .env("PETIT_API_BASE_URL", UNREACHABLE)
+        .output()
+        .await
+        .unwrap();
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output).lines().count(), 3);
+    assert!(stderr(&output).contains("Offline"));
+}
+
+#[tokio::test]
+async fn queued_tweets_are_sent_before_the_next_tweet() {
+    let petit = Petit::start().await;
+    petit.login("alice");
+    Mock::given(method("POST"))
+        .and(path("/1.1/statuses/update.json"))
+        .and(query_param("status", "written offline"))
+        .respond_with(ok("update.json"))
+        .expect(1)
+        .mount(&petit.server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/1.1/statuses/update.json"))
+        .and(query_param("status", "written on a train"))
+        .respond_with(ok("update.json"))
+        .expect(1)
+        .mount(&petit.server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/1.1/statuses/update.json"))
+        .and(query_param("status", "back online"))
+        .respond_with(ok("update.json"))
+        .expect(1)
+        .mount(&petit.server)
+        .await;
+
+    let output = petit.run(&["--offline", "tweet", "written offline"]).await;
+    assert!(output.status.success(), "{}", stderr(&output));
+    let output = petit
+        .command(&["tweet", "written on a train"])
+        .env("PETIT_API_BASE_URL", UNREACHABLE)
+        .output()
+        .await
+        .unwrap();
+    assert!(output.status.success(), "{}", stderr(&output));
+    let output = petit.run(&["outbox", "list"]).await;
+    assert_eq!(stdout(&output).lines().count(), 2);
+
+    let output = petit.run(&["tweet", "back online"]).await;
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "Sent 2 queued tweets\nsuccess!");
+    let output = petit.run(&["outbox", "list"]).await;
+    assert_eq!(stdout(&output), "");
+}
+
+#[tokio::test]
+async fn queued_tweets_stay_queued_while_twitter_is_down() {
+    let petit = Petit::start().await;
+    petit.login("alice");
+    Mock::given(method("POST"))
+        .and(path("/1.1/statuses/update.json"))
+        .respond_with(ResponseTemplate::new(503))
+        .expect(1)
+        .mount(&petit.server)
+        .await;
+
+    let output = petit.run(&["--offline", "tweet", "written offline"]).await;
+    assert!(output.status.success(), "{}", stderr(&output));
+    let output = petit.run(&["outbox", "send"]).await;
+
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("Twitter is unavailable (503"));
+    let output = petit.run(&["outbox", "list"]).await;
+    assert_eq!(stdout(&output).lines().count(), 1);
+}
+
+#[tokio::test]
+async fn tweet_is_queued_behind_the_outbox_while_twitter_is_down() {
+    let petit = Petit::start().await;
+    petit.login("alice");
+    Mock::given(method("POST"))
+        .and(path("/1.1/statuses/update.json"))
+        .respond_with(ResponseTemplate::new(503))
+        .expect(1)
+        .mount(&petit.server)
+        .await;
+
+    let output = petit.run(&["--offline", "tweet", "written offline"]).await;
+    assert!(output.status.success(), "{}", stderr(&output));
+    let output = petit.run(&["tweet", "written while down"]).await;
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stderr(&output).contains("Twitter is unavailable (503"));
+    let output = petit.run(&["outbox", "list"]).await;
+    let out = stdout(&output);
+    assert_eq!(out.lines().count(), 2);
+    assert!(out.contains("written offline"));
+    assert!(out.contains("written while down"));
+}
+
+#[tokio::test]
+async fn tweet_is_sent_after_queued_tweets_rejected_for_their_content() {
+    let petit = Petit::start().await;
+    petit.login("alice");
+    Mock::given(method("POST"))
+        .and(path("/1.1/statuses/update.json"))
+        .and(query_param("status", "said twice"))
+        .respond_with(ResponseTemplate::new(403).set_body_json(serde_json::json!({
+            "errors": [{ "code": 187, "message": "Status is a duplicate." }]
+        })))
+        .expect(1)
+        .mount(&petit.server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/1.1/statuses/update.json"))
+        .and(query_param("status", "something new"))
+        .respond_with(ok("update.json"))
+        .expect(1)
+        .mount(&petit.server)
+        .await;
+
+    let output = petit.run(&["--offline", "tweet", "said twice"]).await;
+    assert!(output.status.success(), "{}", stderr(&output));
+    let output = petit.run(&["tweet", "something new"]).await;
+
+    assert!(!output.status.success());
+    assert_eq!(stdout(&output), "success!");
+    assert!(stderr(&output).contains("1 queued tweet(s) were rejected"));
+}
+
+#[tokio::test]
+async fn queued_tweets_rejected_for_their_content_are_dropped_loudly() {
+    let petit = Petit::start().await;
+    petit.login("alice");
+    Mock::given(method("POST"))
+        .and(path("/1.1/statuses/update.json"))
+        .and(query_param("status", "said twice"))
+        .respond_with(ResponseTemplate::new(403).set_body_json(serde_json::json!({
+            "errors": [{ "code": 187, "message": "Status is a duplicate." }]
+        })))
+        .expect(1)
+        .mount(&petit.server)
+        .await;
+
+    let output = petit.run(&["--offline", "tweet", "said twice"]).await;
+    assert!(output.status.success(), "{}", stderr(&output));
+    let output = petit.run(&["outbox", "send"]).await;
+
+    assert!(!output.status.success());
+    let err = stderr(&output);
+    assert!(err.contains("1 queued tweet(s) were rejected"), "{}", err);
+    assert!(err.contains("said twice"));
+    let output = petit.run(&["outbox", "list"]).await;
+    assert_eq!(stdout(&output), "");
+}